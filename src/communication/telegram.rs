@@ -1,17 +1,35 @@
 use crate::configuration::Context;
 use crate::core::Error;
 use crate::core::Service;
+use crate::core::{Currency, Money};
 use crate::database::DatabaseService;
-use crate::request::RequestFulfilment;
-use crate::request::types::{RecordContext, SessionContext};
+use crate::request::types::{CategoryChoice, ListingPage, Reconciliation, SessionContext};
+use crate::request::{RequestError, RequestFulfilment, ToolError, not_owner_response};
 use async_trait::async_trait;
 use std::env;
 use std::sync::Arc;
+use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::InputFile;
+use teloxide::types::{Document, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Me};
 use thiserror::Error;
 use tokio::sync::mpsc;
 
+// Callback data is "category:<expense_id>:<category>", limited to 64 bytes by Telegram
+const CATEGORY_CALLBACK_PREFIX: &str = "category";
+const MAX_CALLBACK_DATA_LEN: usize = 64;
+// Callback data is "import:confirm:<member_id>" or "import:cancel:<member_id>" for the
+// pending import of the member who sent the file
+const IMPORT_CALLBACK_PREFIX: &str = "import";
+const IMPORT_CONFIRM: &str = "confirm";
+const IMPORT_CANCEL: &str = "cancel";
+// Callback data is "reconcile:<account_id>:<stated amount in minor units>" or "reconcile:cancel"
+const RECONCILE_CALLBACK_PREFIX: &str = "reconcile";
+const RECONCILE_CANCEL: &str = "cancel";
+// Callback data is "page:<listing id>:<page>" for the next/prev buttons of a listing
+const PAGE_CALLBACK_PREFIX: &str = "page";
+const MAX_IMPORT_FILE_BYTES: u32 = 1024 * 1024;
+const IMPORT_EXTENSIONS: &[&str] = &[".csv", ".xls", ".xlsx"];
+
 #[derive(Debug, Error)]
 pub enum TelegramServiceError {
     #[error("Initialization Error")]
@@ -49,22 +67,46 @@ impl Service for TelegramService {
         let error_channel = Arc::new(self.error_channel);
         let request_fulfilment = Arc::new(self.request_fulfilment);
         let database = self.database;
-        teloxide::repl(self.bot, move |bot: Bot, msg: Message| {
-            let error_channel = Arc::clone(&error_channel);
-            let request_fulfilment = Arc::clone(&request_fulfilment);
-            let database = database.clone();
-            async move {
-                tokio::spawn(Self::handle_message(
-                    bot,
-                    msg,
-                    request_fulfilment,
-                    database,
-                    error_channel,
-                ));
-                respond(())
-            }
-        })
-        .await;
+        let handler = dptree::entry()
+            .branch(Update::filter_message().endpoint(
+                |bot: Bot,
+                 me: Me,
+                 msg: Message,
+                 request_fulfilment: Arc<RequestFulfilment>,
+                 database: Arc<DatabaseService>,
+                 error_channel: Arc<mpsc::Sender<String>>| async move {
+                    tokio::spawn(Self::handle_message(
+                        bot,
+                        me,
+                        msg,
+                        request_fulfilment,
+                        database,
+                        error_channel,
+                    ));
+                    respond(())
+                },
+            ))
+            .branch(Update::filter_callback_query().endpoint(
+                |bot: Bot,
+                 query: CallbackQuery,
+                 request_fulfilment: Arc<RequestFulfilment>,
+                 error_channel: Arc<mpsc::Sender<String>>| async move {
+                    tokio::spawn(Self::handle_callback_query(
+                        bot,
+                        query,
+                        request_fulfilment,
+                        error_channel,
+                    ));
+                    respond(())
+                },
+            ));
+
+        Dispatcher::builder(self.bot, handler)
+            .dependencies(dptree::deps![request_fulfilment, database, error_channel])
+            .enable_ctrlc_handler()
+            .build()
+            .dispatch()
+            .await;
         Ok(())
     }
 }
@@ -74,8 +116,23 @@ impl TelegramService {
         std::fs::read_to_string("assets/help.txt")
     }
 
+    // Commands in groups are often sent as "/export@BotName", the mention is dropped
+    // when it names this bot so the command matches as usual
+    fn strip_bot_mention(text: &str, bot_username: &str) -> String {
+        let (command, rest) = text.split_at(text.find(' ').unwrap_or(text.len()));
+        match command.split_once('@') {
+            Some((name, mention))
+                if name.starts_with('/') && mention.eq_ignore_ascii_case(bot_username) =>
+            {
+                format!("{}{}", name, rest)
+            }
+            _ => text.to_string(),
+        }
+    }
+
     async fn handle_message(
         bot: Bot,
+        me: Me,
         msg: Message,
         request_fulfilment: Arc<RequestFulfilment>,
         database: Arc<DatabaseService>,
@@ -83,11 +140,24 @@ impl TelegramService {
     ) -> ResponseResult<()> {
         let chat_id = msg.chat.id;
         let user_id = chat_id.0;
+        // In a group the ledger belongs to the chat, entries are attributed to the sender
+        let member_id = msg.from().map(|user| user.id.0 as i64);
+        if let Some(user) = msg.from()
+            && let Err(e) = database
+                .record_member(user_id, user.id.0 as i64, &user.full_name())
+                .await
+        {
+            let _ = error_channel
+                .send(format!("Failed to record chat member: {}", e))
+                .await;
+        }
+
+        let command = msg
+            .text()
+            .map(|text| Self::strip_bot_mention(text, me.username()));
 
         // Handle /help command
-        if let Some(text) = msg.text()
-            && text == "/help"
-        {
+        if command.as_deref() == Some("/help") {
             match Self::get_help_text() {
                 Ok(help_text) => {
                     let _ = bot.send_message(chat_id, help_text).await;
@@ -100,40 +170,115 @@ impl TelegramService {
                 }
             }
         }
-        let replied_record = if let Some(reply_to) = msg.reply_to_message() {
+
+        // Handle /export command
+        if let Some(text) = command.as_deref()
+            && let Some(args) = text.strip_prefix("/export")
+            && (args.is_empty() || args.starts_with(' '))
+        {
+            let session_context = SessionContext {
+                user_id,
+                member_id,
+                user_message_id: msg.id.0 as i64,
+                replied_record: None,
+            };
+            match request_fulfilment.export(args, &session_context).await {
+                Ok(export) => {
+                    let document = InputFile::memory(export.content).file_name(export.file_name);
+                    if let Err(e) = bot
+                        .send_document(chat_id, document)
+                        .caption(export.summary)
+                        .await
+                    {
+                        let _ = error_channel
+                            .send(format!("Failed to send export: {}", e))
+                            .await;
+                    }
+                }
+                Err(RequestError::InvalidRequest(usage)) => {
+                    let _ = bot.send_message(chat_id, usage).await;
+                }
+                Err(e) => {
+                    let _ = error_channel.send(format!("Export error: {}", e)).await;
+                    let _ = bot
+                        .send_message(chat_id, "Sorry, could not export your data.")
+                        .await;
+                }
+            }
+            return Ok(());
+        }
+
+        // Uploaded files are imports
+        if let Some(document) = msg.document() {
+            let session_context = SessionContext {
+                user_id,
+                member_id,
+                user_message_id: msg.id.0 as i64,
+                replied_record: None,
+            };
+            Self::handle_document(
+                &bot,
+                document,
+                &session_context,
+                &request_fulfilment,
+                &error_channel,
+            )
+            .await;
+            return Ok(());
+        }
+
+        // Handle /undo command
+        if command.as_deref() == Some("/undo") {
+            let session_context = SessionContext {
+                user_id,
+                member_id,
+                user_message_id: msg.id.0 as i64,
+                replied_record: None,
+            };
+            let response = match request_fulfilment.undo_last_action(&session_context).await {
+                Ok(response) => response,
+                Err(e) => {
+                    let _ = error_channel.send(format!("Undo error: {}", e)).await;
+                    "Sorry, could not undo the last action.".to_string()
+                }
+            };
+            let _ = bot.send_message(chat_id, response).await;
+            return Ok(());
+        }
+        let mut replied_records = if let Some(reply_to) = msg.reply_to_message() {
             let replied_msg_id = reply_to.id.0 as i64;
 
-            // Try to find expense first
+            // Expenses, cash transactions or transfers the replied message belongs to
             match database
-                .find_expense_by_message(user_id, replied_msg_id)
+                .find_records_by_message(user_id, replied_msg_id)
                 .await
             {
-                Ok(Some(expense)) => Some(RecordContext::Expense(expense)),
-                Ok(None) => {
-                    // Try to find cash transaction
-                    match database.find_cash_by_message(user_id, replied_msg_id).await {
-                        Ok(Some(cash)) => Some(RecordContext::CashTransaction(cash)),
-                        Ok(None) => None,
-                        Err(e) => {
-                            let _ = error_channel
-                                .send(format!("Database lookup error: {}", e))
-                                .await;
-                            None
-                        }
-                    }
-                }
+                Ok(records) => records,
                 Err(e) => {
                     let _ = error_channel
                         .send(format!("Database lookup error: {}", e))
                         .await;
-                    None
+                    Vec::new()
                 }
             }
         } else {
-            None
+            Vec::new()
         };
+        // A reply to a message with several entries could act on the wrong one, so the
+        // user is asked to name it instead
+        if replied_records.len() > 1 {
+            let _ = bot
+                .send_message(
+                    chat_id,
+                    RequestFulfilment::choose_record_response(&replied_records),
+                )
+                .await;
+            return Ok(());
+        }
+        let replied_record = replied_records.pop();
         let session_context = SessionContext {
             user_id: chat_id.0,
+            member_id,
             user_message_id: msg.id.0 as i64,
             replied_record,
         };
@@ -143,19 +288,35 @@ impl TelegramService {
                 .await
             {
                 Ok(result) => {
+                    let keyboard = result
+                        .category_choice
+                        .as_ref()
+                        .map(Self::category_keyboard)
+                        .or_else(|| {
+                            result
+                                .reconciliation
+                                .as_ref()
+                                .map(Self::reconciliation_keyboard)
+                        })
+                        .or_else(|| result.listing_page.as_ref().map(Self::listing_keyboard));
                     // Send response with or without image
                     let sent_msg_result = if let Some(image_data) = result.image {
                         // Send photo with caption
                         let photo = InputFile::memory(image_data);
-                        bot.send_photo(chat_id, photo)
-                            .caption(result.response)
-                            .await
-                            .map(|msg| msg.id)
+                        let request = bot.send_photo(chat_id, photo).caption(result.response);
+                        match keyboard {
+                            Some(keyboard) => request.reply_markup(keyboard).await,
+                            None => request.await,
+                        }
+                        .map(|msg| msg.id)
                     } else {
                         // Send text message
-                        bot.send_message(chat_id, result.response)
-                            .await
-                            .map(|msg| msg.id)
+                        let request = bot.send_message(chat_id, result.response);
+                        match keyboard {
+                            Some(keyboard) => request.reply_markup(keyboard).await,
+                            None => request.await,
+                        }
+                        .map(|msg| msg.id)
                     };
 
                     match sent_msg_result {
@@ -195,4 +356,297 @@ impl TelegramService {
         }
         Ok(())
     }
+
+    // Chosen category marked with a tick, followed by the user's top alternatives
+    fn category_keyboard(choice: &CategoryChoice) -> InlineKeyboardMarkup {
+        let button = |label: String, category: &str| {
+            let data = format!(
+                "{}:{}:{}",
+                CATEGORY_CALLBACK_PREFIX, choice.expense_id, category
+            );
+            (data.len() <= MAX_CALLBACK_DATA_LEN)
+                .then(|| InlineKeyboardButton::callback(label, data))
+        };
+        let buttons: Vec<InlineKeyboardButton> =
+            std::iter::once(button(format!("✓ {}", choice.category), &choice.category))
+                .chain(
+                    choice
+                        .alternatives
+                        .iter()
+                        .map(|category| button(category.clone(), category)),
+                )
+                .flatten()
+                .collect();
+        InlineKeyboardMarkup::new(vec![buttons])
+    }
+
+    // Confirms or dismisses the adjustment for a stated balance
+    fn reconciliation_keyboard(reconciliation: &Reconciliation) -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback(
+                "✅ Record adjustment",
+                format!(
+                    "{}:{}:{}",
+                    RECONCILE_CALLBACK_PREFIX,
+                    reconciliation.account_id,
+                    reconciliation.stated.minor()
+                ),
+            ),
+            InlineKeyboardButton::callback(
+                "✖️ Keep as is",
+                format!("{}:{}", RECONCILE_CALLBACK_PREFIX, RECONCILE_CANCEL),
+            ),
+        ]])
+    }
+
+    // Buttons for the pages around the current one of a listing
+    fn listing_keyboard(page: &ListingPage) -> InlineKeyboardMarkup {
+        let button = |label: &str, target: usize| {
+            InlineKeyboardButton::callback(
+                label,
+                format!("{}:{}:{}", PAGE_CALLBACK_PREFIX, page.listing_id, target),
+            )
+        };
+        let mut buttons = Vec::new();
+        if page.page > 0 {
+            buttons.push(button("◀️ Prev", page.page - 1));
+        }
+        if page.page + 1 < page.pages {
+            buttons.push(button("Next ▶️", page.page + 1));
+        }
+        InlineKeyboardMarkup::new(vec![buttons])
+    }
+
+    async fn handle_callback_query(
+        bot: Bot,
+        query: CallbackQuery,
+        request_fulfilment: Arc<RequestFulfilment>,
+        error_channel: Arc<mpsc::Sender<String>>,
+    ) -> ResponseResult<()> {
+        let _ = bot.answer_callback_query(query.id.clone()).await;
+        let (Some(data), Some(msg)) = (query.data, query.message) else {
+            return Ok(());
+        };
+        let Some((prefix, payload)) = data.split_once(':') else {
+            return Ok(());
+        };
+
+        let chat_id = msg.chat.id;
+        // The member who pressed the button, not the bot that sent the message
+        let session_context = SessionContext {
+            user_id: chat_id.0,
+            member_id: Some(query.from.id.0 as i64),
+            user_message_id: msg.id.0 as i64,
+            replied_record: None,
+        };
+        // Paging keeps the keyboard, unlike the other buttons which settle the message
+        if prefix == PAGE_CALLBACK_PREFIX {
+            let Some((listing_id, page)) = payload
+                .split_once(':')
+                .and_then(|(id, page)| Some((id.parse().ok()?, page.parse().ok()?)))
+            else {
+                return Ok(());
+            };
+            let edit_result =
+                match request_fulfilment.listing_page(listing_id, page, &session_context) {
+                    Some((text, page)) => {
+                        bot.edit_message_text(chat_id, msg.id, text)
+                            .reply_markup(Self::listing_keyboard(&page))
+                            .await
+                    }
+                    None => {
+                        let _ = bot.edit_message_reply_markup(chat_id, msg.id).await;
+                        bot.send_message(chat_id, "This list has expired, please ask again")
+                            .await
+                    }
+                };
+            if let Err(e) = edit_result {
+                let _ = error_channel
+                    .send(format!("Failed to show listing page: {}", e))
+                    .await;
+            }
+            return Ok(());
+        }
+
+        let result = match prefix {
+            CATEGORY_CALLBACK_PREFIX => {
+                let Some((expense_id, category)) = payload
+                    .split_once(':')
+                    .and_then(|(id, category)| Some((id.parse::<i64>().ok()?, category)))
+                else {
+                    return Ok(());
+                };
+                request_fulfilment
+                    .change_expense_category(
+                        expense_id,
+                        category,
+                        msg.id.0 as i64,
+                        &session_context,
+                    )
+                    .await
+                    .map(|response| {
+                        response.unwrap_or_else(|| "This expense no longer exists".to_string())
+                    })
+            }
+            IMPORT_CALLBACK_PREFIX => {
+                let (action, uploader) = payload.split_once(':').unwrap_or((payload, ""));
+                // Another member's preview keeps its keyboard for the member who sent the file
+                if uploader
+                    .parse::<i64>()
+                    .is_ok_and(|uploader| uploader != session_context.acting_member())
+                {
+                    let _ = bot
+                        .send_message(
+                            chat_id,
+                            "🔒 Only the member who sent the file can import or cancel it",
+                        )
+                        .await;
+                    return Ok(());
+                }
+                match action {
+                    IMPORT_CONFIRM => request_fulfilment.confirm_import(&session_context).await,
+                    _ => Ok(request_fulfilment.cancel_import(&session_context)),
+                }
+            }
+            RECONCILE_CALLBACK_PREFIX => {
+                if payload == RECONCILE_CANCEL {
+                    Ok("Balance left as it is".to_string())
+                } else {
+                    let Some(reconciliation) = payload.split_once(':').and_then(|(id, minor)| {
+                        Some(Reconciliation {
+                            account_id: id.parse().ok()?,
+                            stated: Money::from_minor(minor.parse().ok()?, Currency::default()),
+                        })
+                    }) else {
+                        return Ok(());
+                    };
+                    request_fulfilment
+                        .confirm_reconciliation(&reconciliation, &session_context)
+                        .await
+                }
+            }
+            _ => return Ok(()),
+        };
+        let response = match result {
+            Ok(response) => response,
+            // Another member's expense keeps its keyboard for the member who added it
+            Err(RequestError::ToolError(ToolError::NotExpenseOwner(owner))) => {
+                let _ = bot.send_message(chat_id, not_owner_response(&owner)).await;
+                return Ok(());
+            }
+            Err(e) => {
+                let _ = error_channel
+                    .send(format!("Callback query error: {}", e))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        // Editing without a reply markup also removes the keyboard
+        let edit_result = if msg.photo().is_some() {
+            bot.edit_message_caption(chat_id, msg.id)
+                .caption(response)
+                .await
+        } else {
+            bot.edit_message_text(chat_id, msg.id, response).await
+        };
+        if let Err(e) = edit_result {
+            let _ = error_channel
+                .send(format!("Failed to edit message: {}", e))
+                .await;
+        }
+        Ok(())
+    }
+
+    // Uploaded CSV and Excel files are previewed as an import awaiting confirmation
+    async fn handle_document(
+        bot: &Bot,
+        document: &Document,
+        ctx: &SessionContext,
+        request_fulfilment: &RequestFulfilment,
+        error_channel: &mpsc::Sender<String>,
+    ) {
+        let chat_id = ChatId(ctx.user_id);
+        let is_importable = document.file_name.as_deref().is_some_and(|name| {
+            let name = name.to_lowercase();
+            IMPORT_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+        }) || document
+            .mime_type
+            .as_ref()
+            .is_some_and(|mime| mime.essence_str() == "text/csv");
+        if !is_importable {
+            let _ = bot
+                .send_message(
+                    chat_id,
+                    "Send a CSV or Excel file to import expenses or a bank statement",
+                )
+                .await;
+            return;
+        }
+        if document.file.size > MAX_IMPORT_FILE_BYTES {
+            let _ = bot
+                .send_message(chat_id, "The file is too large, the limit is 1 MB")
+                .await;
+            return;
+        }
+
+        let mut content = Vec::new();
+        let download = match bot.get_file(document.file.id.clone()).await {
+            Ok(file) => bot
+                .download_file(&file.path, &mut content)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = download {
+            let _ = error_channel
+                .send(format!("Failed to download import file: {}", e))
+                .await;
+            let _ = bot
+                .send_message(chat_id, "Sorry, could not download the file.")
+                .await;
+            return;
+        }
+
+        match request_fulfilment.prepare_import(&content, ctx).await {
+            Ok(preview) if preview.pending => {
+                let keyboard = InlineKeyboardMarkup::new(vec![vec![
+                    InlineKeyboardButton::callback(
+                        "✅ Import",
+                        format!(
+                            "{}:{}:{}",
+                            IMPORT_CALLBACK_PREFIX,
+                            IMPORT_CONFIRM,
+                            ctx.acting_member()
+                        ),
+                    ),
+                    InlineKeyboardButton::callback(
+                        "✖️ Cancel",
+                        format!(
+                            "{}:{}:{}",
+                            IMPORT_CALLBACK_PREFIX,
+                            IMPORT_CANCEL,
+                            ctx.acting_member()
+                        ),
+                    ),
+                ]]);
+                let _ = bot
+                    .send_message(chat_id, preview.response)
+                    .reply_markup(keyboard)
+                    .await;
+            }
+            Ok(preview) => {
+                let _ = bot.send_message(chat_id, preview.response).await;
+            }
+            Err(RequestError::InvalidRequest(message)) => {
+                let _ = bot.send_message(chat_id, message).await;
+            }
+            Err(e) => {
+                let _ = error_channel.send(format!("Import error: {}", e)).await;
+                let _ = bot
+                    .send_message(chat_id, "Sorry, could not read the file.")
+                    .await;
+            }
+        }
+    }
 }
@@ -13,15 +13,62 @@ pub enum ConfigError {
     #[error("Deserialization error:{0}")]
     DeserializationError(String),
 
-    #[error("Database init error")]
-    DatabaseServiceInitError,
+    #[error("Database init error: {0}")]
+    DatabaseServiceInitError(String),
 }
 
 #[derive(Debug, Deserialize, Clone)]
 
 pub struct Config {
     pub log_level: String,
-    pub db_url: String,
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub llm: LlmConfig,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum DatabaseConfig {
+    // Local SQLite file, no Turso account needed
+    Local {
+        path: String,
+    },
+    // Throwaway in-memory database
+    Memory,
+    // Turso embedded replica: reads from a local file, writes go to the remote primary
+    Replica {
+        path: String,
+        url: String,
+        sync_interval_secs: Option<u64>,
+    },
+    // All queries go to the remote Turso database
+    Remote {
+        url: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum LlmConfig {
+    Groq {
+        model: String,
+    },
+    OpenaiCompatible {
+        base_url: String,
+        model: String,
+        api_key_env: Option<String>,
+    },
+    Scripted {
+        script_file: String,
+    },
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        LlmConfig::Groq {
+            model: "openai/gpt-oss-20b".to_string(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -34,9 +81,9 @@ impl Context {
     pub async fn new(config_file: &str) -> Result<Self, ConfigError> {
         let config = Config::new(config_file)?;
         let database = Arc::new(
-            DatabaseService::new(config.db_url.clone())
+            DatabaseService::new(&config.database)
                 .await
-                .map_err(|_| ConfigError::DatabaseServiceInitError)?,
+                .map_err(|e| ConfigError::DatabaseServiceInitError(e.to_string()))?,
         );
         Ok(Self { config, database })
     }
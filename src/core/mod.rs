@@ -1,11 +1,14 @@
 use thiserror::Error;
 mod cache;
+pub mod csv;
 mod http;
+mod money;
 mod service;
 mod service_manager;
 
 pub use cache::ExpirableCache;
 pub use http::RetryableClient;
+pub use money::{Currency, Money, ParseMoneyError};
 pub use service::{Service, ServiceWithReceiver};
 pub use service_manager::ServiceManager;
 
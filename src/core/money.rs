@@ -0,0 +1,281 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Currency {
+    #[default]
+    Inr,
+}
+
+impl Currency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Inr => "INR",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Inr => "₹",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.to_uppercase().as_str() {
+            "INR" => Some(Currency::Inr),
+            _ => None,
+        }
+    }
+}
+
+const MINOR_UNITS: i64 = 100;
+
+// Amount in integer minor units (paise for INR) so 49.50 is stored exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Money {
+    minor: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn from_minor(minor: i64, currency: Currency) -> Self {
+        Self { minor, currency }
+    }
+
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn minor(&self) -> i64 {
+        self.minor
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor < 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::from_minor(self.minor.abs(), self.currency)
+    }
+
+    // Major units as a float, only for ratios and charts
+    pub fn as_f64(&self) -> f64 {
+        self.minor as f64 / MINOR_UNITS as f64
+    }
+
+    // Amount without the currency symbol, e.g. "49.50" or "500"
+    pub fn amount_string(&self) -> String {
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let major = self.minor.abs() / MINOR_UNITS;
+        let fraction = self.minor.abs() % MINOR_UNITS;
+        if fraction == 0 {
+            format!("{}{}", sign, major)
+        } else {
+            format!("{}{}.{:02}", sign, major, fraction)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseMoneyError(String);
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid amount '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseMoneyError {}
+
+// Parses decimal strings like "500", "-200", "49.5", "₹49.50" without going through floats
+impl FromStr for Money {
+    type Err = ParseMoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseMoneyError(s.to_string());
+        let trimmed = s.trim().replace(',', "");
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.trim_start_matches('+')),
+        };
+        let digits = digits.trim_start_matches('₹');
+        let (major, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if (major.is_empty() && fraction.is_empty())
+            || !major.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+            || fraction.len() > 2
+        {
+            return Err(error());
+        }
+        let major: i64 = if major.is_empty() {
+            0
+        } else {
+            major.parse().map_err(|_| error())?
+        };
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| error())?;
+        let minor = major
+            .checked_mul(MINOR_UNITS)
+            .and_then(|m| m.checked_add(fraction))
+            .ok_or_else(error)?;
+        Ok(Self::from_minor(
+            if negative { -minor } else { minor },
+            Currency::default(),
+        ))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.minor < 0 {
+            write!(
+                f,
+                "-{}{}",
+                self.currency.symbol(),
+                self.abs().amount_string()
+            )
+        } else {
+            write!(f, "{}{}", self.currency.symbol(), self.amount_string())
+        }
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money::from_minor(self.minor + rhs.minor, self.currency)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.minor += rhs.minor;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money::from_minor(self.minor - rhs.minor, self.currency)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::from_minor(-self.minor, self.currency)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), |acc, m| acc + m)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+// Serialized as a plain decimal number of major units
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_f64())
+    }
+}
+
+// Accepts JSON numbers (500, 49.5) and strings ("49.50") as the LLM may send either
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MoneyVisitor;
+
+        impl Visitor<'_> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an amount with at most two decimal places")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+                v.checked_mul(MINOR_UNITS)
+                    .map(|minor| Money::from_minor(minor, Currency::default()))
+                    .ok_or_else(|| E::custom(format!("amount {} is too large", v)))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+                let v = i64::try_from(v).map_err(|_| E::custom("amount is too large"))?;
+                self.visit_i64(v)
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+                // Shortest round-trip representation, so 49.5 parses as "49.5"
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minor(s: &str) -> Result<i64, ParseMoneyError> {
+        s.parse::<Money>().map(|m| m.minor())
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(minor("500"), Ok(50000));
+        assert_eq!(minor(" 49.5 "), Ok(4950));
+        assert_eq!(minor("49.50"), Ok(4950));
+        assert_eq!(minor(".5"), Ok(50));
+        assert_eq!(minor("5."), Ok(500));
+    }
+
+    #[test]
+    fn parses_commas_and_symbol() {
+        assert_eq!(minor("1,234.50"), Ok(123450));
+        assert_eq!(minor("1,00,000"), Ok(10000000));
+        assert_eq!(minor("₹500"), Ok(50000));
+        assert_eq!(minor("₹1,000.25"), Ok(100025));
+    }
+
+    #[test]
+    fn parses_signs() {
+        assert_eq!(minor("-200"), Ok(-20000));
+        assert_eq!(minor("+75"), Ok(7500));
+        assert_eq!(minor("-₹20.5"), Ok(-2050));
+        assert_eq!(minor("+₹20"), Ok(2000));
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        for s in [
+            "", ".", "-", "+", "₹", "-.", "49.505", "1.2.3", "--5", "5-", "abc", "12a",
+        ] {
+            assert!(s.parse::<Money>().is_err(), "{}", s);
+        }
+    }
+}
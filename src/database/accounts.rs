@@ -0,0 +1,381 @@
+use super::{
+    Account, AccountBalance, AccountKind, DatabaseError, DatabaseService, JournalAction, Transfer,
+};
+use crate::core::{Currency, Money};
+use crate::request::types::{
+    ActionType, SessionContext,
+    args::{AddTransferArgs, ModifyTransferArgs},
+};
+use libsql::params;
+use std::collections::HashMap;
+
+// Every user has this account, all entries made before accounts existed belong to it
+pub const DEFAULT_ACCOUNT: &str = "Cash";
+
+const TRANSFER_SELECT: &str = "SELECT id, user_id, from_account, to_account, amount_minor, currency, transfer_date, user_message_id, bot_message_id, created_at
+     FROM transfers";
+
+impl DatabaseService {
+    // Accounts in creation order, the default Cash account is created on first use
+    pub async fn get_accounts(&self, user_id: i64) -> Result<Vec<Account>, DatabaseError> {
+        let accounts = self.query_accounts(user_id).await?;
+        if !accounts.is_empty() {
+            return Ok(accounts);
+        }
+        self.execute(
+            "INSERT OR IGNORE INTO accounts (user_id, name, kind, is_default, created_at)
+             VALUES (?, ?, ?, 1, datetime('now'))",
+            params![user_id, DEFAULT_ACCOUNT, AccountKind::Cash.as_str()],
+        )
+        .await?;
+        self.query_accounts(user_id).await
+    }
+
+    async fn query_accounts(&self, user_id: i64) -> Result<Vec<Account>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, name, kind, is_default, created_at
+                 FROM accounts
+                 WHERE user_id = ?
+                 ORDER BY id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut accounts = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            accounts.push(Account::from_row(&row)?);
+        }
+        Ok(accounts)
+    }
+
+    // Account names are matched case-insensitively
+    pub async fn find_account(
+        &self,
+        user_id: i64,
+        name: &str,
+    ) -> Result<Option<Account>, DatabaseError> {
+        Ok(self
+            .get_accounts(user_id)
+            .await?
+            .into_iter()
+            .find(|a| a.name.eq_ignore_ascii_case(name.trim())))
+    }
+
+    // Stored name of the requested account, or of the default account when None
+    pub async fn account_name(
+        &self,
+        user_id: i64,
+        requested: Option<&str>,
+    ) -> Result<String, DatabaseError> {
+        let accounts = self.get_accounts(user_id).await?;
+        let account = match requested {
+            Some(name) => accounts
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| DatabaseError::UnknownAccount(name.to_string()))?,
+            None => accounts
+                .iter()
+                .find(|a| a.is_default)
+                .or(accounts.first())
+                .ok_or_else(|| DatabaseError::UnknownAccount(DEFAULT_ACCOUNT.to_string()))?,
+        };
+        Ok(account.name.clone())
+    }
+
+    // Returns false if an account with that name already exists
+    pub async fn add_account(
+        &self,
+        user_id: i64,
+        name: &str,
+        kind: AccountKind,
+    ) -> Result<bool, DatabaseError> {
+        // Make sure the default account exists before the first extra account
+        self.get_accounts(user_id).await?;
+        let created = self
+            .execute_returning_count(
+                "INSERT OR IGNORE INTO accounts (user_id, name, kind, is_default, created_at)
+                 VALUES (?, ?, ?, 0, datetime('now'))",
+                params![user_id, name.trim(), kind.as_str()],
+            )
+            .await?;
+        Ok(created > 0)
+    }
+
+    // Returns the stored name of the new default account, None if it does not exist
+    pub async fn set_default_account(
+        &self,
+        user_id: i64,
+        name: &str,
+    ) -> Result<Option<String>, DatabaseError> {
+        let Some(account) = self.find_account(user_id, name).await? else {
+            return Ok(None);
+        };
+        self.execute(
+            "UPDATE accounts SET is_default = (id = ?) WHERE user_id = ?",
+            params![account.id, user_id],
+        )
+        .await?;
+        Ok(Some(account.name))
+    }
+
+    // Balance of every account: cash entries, income, settlements and incoming transfers
+    // minus expenses and outgoing transfers
+    pub async fn get_account_balances(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<AccountBalance>, DatabaseError> {
+        let accounts = self.get_accounts(user_id).await?;
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT account, COALESCE(SUM(amount), 0) FROM (
+                    SELECT account, amount_minor AS amount FROM cash_transactions WHERE user_id = ?
+                    UNION ALL
+                    SELECT account, amount_minor FROM income WHERE user_id = ?
+                    UNION ALL
+                    SELECT account, amount_minor FROM settlements WHERE user_id = ?
+                    UNION ALL
+                    SELECT account, -amount_minor FROM expenses WHERE user_id = ? AND deleted_at IS NULL
+                    UNION ALL
+                    SELECT to_account, amount_minor FROM transfers WHERE user_id = ?
+                    UNION ALL
+                    SELECT from_account, -amount_minor FROM transfers WHERE user_id = ?
+                 )
+                 GROUP BY account COLLATE NOCASE",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id, user_id, user_id, user_id, user_id, user_id
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut totals = HashMap::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            let account: String = row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            let total: i64 = row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            totals.insert(account.to_lowercase(), total);
+        }
+        Ok(accounts
+            .into_iter()
+            .map(|account| {
+                let total = totals
+                    .get(&account.name.to_lowercase())
+                    .copied()
+                    .unwrap_or(0);
+                AccountBalance {
+                    account,
+                    balance: Money::from_minor(total, Currency::default()),
+                }
+            })
+            .collect())
+    }
+
+    pub async fn add_transfer(
+        &self,
+        args: &AddTransferArgs,
+        ctx: &SessionContext,
+    ) -> Result<i64, DatabaseError> {
+        let from_account = self
+            .account_name(ctx.user_id, Some(&args.from_account))
+            .await?;
+        let to_account = self
+            .account_name(ctx.user_id, Some(&args.to_account))
+            .await?;
+        let transfer_id = self
+            .execute_returning_id(
+                "INSERT INTO transfers (user_id, from_account, to_account, amount_minor, currency, transfer_date, user_message_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+                params![
+                    ctx.user_id,
+                    from_account,
+                    to_account,
+                    args.amount.minor(),
+                    args.amount.currency().code(),
+                    args.date.to_string(),
+                    ctx.user_message_id
+                ],
+            )
+            .await?;
+        self.record_action(
+            ctx,
+            JournalAction::Create,
+            &ActionType::Transfer,
+            transfer_id,
+            None,
+        )
+        .await?;
+        Ok(transfer_id)
+    }
+
+    pub async fn modify_transfer(
+        &self,
+        args: ModifyTransferArgs,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let mut set_clauses = Vec::new();
+        let mut values: Vec<libsql::Value> = Vec::new();
+
+        if let Some(amt) = args.amount {
+            set_clauses.push("amount_minor = ?");
+            values.push(amt.minor().into());
+        }
+        if let Some(from_account) = &args.from_account {
+            set_clauses.push("from_account = ?");
+            values.push(
+                self.account_name(ctx.user_id, Some(from_account))
+                    .await?
+                    .into(),
+            );
+        }
+        if let Some(to_account) = &args.to_account {
+            set_clauses.push("to_account = ?");
+            values.push(
+                self.account_name(ctx.user_id, Some(to_account))
+                    .await?
+                    .into(),
+            );
+        }
+        if let Some(d) = args.date {
+            set_clauses.push("transfer_date = ?");
+            values.push(d.to_string().into());
+        }
+
+        if set_clauses.is_empty() {
+            return Err(DatabaseError::QueryError("No fields to update".to_string()));
+        }
+
+        let previous_state = self
+            .snapshot_record(&ActionType::Transfer, ctx.user_id, args.transfer_id)
+            .await?;
+        let sql = format!(
+            "UPDATE transfers SET {} WHERE id = ? AND user_id = ?",
+            set_clauses.join(", ")
+        );
+        values.push(args.transfer_id.into());
+        values.push(ctx.user_id.into());
+        self.execute(&sql, libsql::params::Params::Positional(values))
+            .await?;
+        self.journal_change(
+            ctx,
+            JournalAction::Modify,
+            &ActionType::Transfer,
+            args.transfer_id,
+            previous_state,
+        )
+        .await
+    }
+
+    pub async fn delete_transfer(
+        &self,
+        transfer_id: i64,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let previous_state = self
+            .snapshot_record(&ActionType::Transfer, ctx.user_id, transfer_id)
+            .await?;
+        self.execute(
+            "DELETE FROM transfers WHERE id = ? AND user_id = ?",
+            params![transfer_id, ctx.user_id],
+        )
+        .await?;
+        self.journal_change(
+            ctx,
+            JournalAction::Delete,
+            &ActionType::Transfer,
+            transfer_id,
+            previous_state,
+        )
+        .await
+    }
+
+    pub async fn update_transfer_bot_message(
+        &self,
+        transfer_id: i64,
+        bot_message_id: i64,
+    ) -> Result<(), DatabaseError> {
+        self.execute(
+            "UPDATE transfers SET bot_message_id = ? WHERE id = ?",
+            params![bot_message_id, transfer_id],
+        )
+        .await
+    }
+
+    pub async fn get_transfer(
+        &self,
+        user_id: i64,
+        transfer_id: i64,
+    ) -> Result<Option<Transfer>, DatabaseError> {
+        Ok(self
+            .query_transfers(
+                &format!("{} WHERE user_id = ? AND id = ?", TRANSFER_SELECT),
+                params![user_id, transfer_id],
+            )
+            .await?
+            .pop())
+    }
+
+    pub async fn find_transfers_by_message(
+        &self,
+        user_id: i64,
+        message_id: i64,
+    ) -> Result<Vec<Transfer>, DatabaseError> {
+        self.query_transfers(
+            &format!(
+                "{} WHERE user_id = ? AND (user_message_id = ? OR bot_message_id = ?) ORDER BY id",
+                TRANSFER_SELECT
+            ),
+            params![user_id, message_id, message_id],
+        )
+        .await
+    }
+
+    async fn query_transfers(
+        &self,
+        sql: &str,
+        params: impl libsql::params::IntoParams,
+    ) -> Result<Vec<Transfer>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(sql)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut transfers = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            transfers.push(Transfer::from_row(&row)?);
+        }
+        Ok(transfers)
+    }
+}
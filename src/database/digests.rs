@@ -0,0 +1,122 @@
+use super::{DatabaseError, DatabaseService, DigestFrequency, DigestSubscription, Expense};
+use chrono::NaiveDate;
+use libsql::params;
+
+impl DatabaseService {
+    // Subscribe to a digest or change its frequency, replacing any earlier schedule
+    pub async fn set_digest(
+        &self,
+        user_id: i64,
+        frequency: DigestFrequency,
+        next_run_date: NaiveDate,
+    ) -> Result<(), DatabaseError> {
+        self.execute(
+            "INSERT INTO digest_subscriptions (user_id, frequency, next_run_date, created_at)
+             VALUES (?, ?, ?, datetime('now'))
+             ON CONFLICT (user_id) DO UPDATE SET frequency = excluded.frequency, next_run_date = excluded.next_run_date",
+            params![user_id, frequency.as_str(), next_run_date.to_string()],
+        )
+        .await
+    }
+
+    // Returns false if the user had no digest
+    pub async fn delete_digest(&self, user_id: i64) -> Result<bool, DatabaseError> {
+        let deleted = self
+            .execute_returning_count(
+                "DELETE FROM digest_subscriptions WHERE user_id = ?",
+                params![user_id],
+            )
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    // Get digests for all users whose next run is on or before the date
+    pub async fn get_due_digests(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<DigestSubscription>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT user_id, frequency, next_run_date
+                 FROM digest_subscriptions
+                 WHERE next_run_date <= ?
+                 ORDER BY next_run_date, user_id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![date.to_string()])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut digests = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            digests.push(DigestSubscription::from_row(&row)?);
+        }
+        Ok(digests)
+    }
+
+    // Move the next run date forward, returns false if another run already advanced it
+    pub async fn advance_digest(
+        &self,
+        user_id: i64,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<bool, DatabaseError> {
+        let updated = self
+            .execute_returning_count(
+                "UPDATE digest_subscriptions SET next_run_date = ?
+                 WHERE user_id = ? AND next_run_date = ?",
+                params![to.to_string(), user_id, from.to_string()],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    // Largest expenses in the date range, most expensive first
+    pub async fn get_top_expenses(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        limit: i64,
+    ) -> Result<Vec<Expense>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, amount_minor, description, category, expense_date, user_message_id, bot_message_id, created_at, currency, account, member_id
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL AND expense_date BETWEEN ? AND ?
+                 ORDER BY amount_minor DESC, expense_date, id
+                 LIMIT ?",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start_date.to_string(),
+                end_date.to_string(),
+                limit
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut expenses = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            expenses.push(Expense::from_row(&row)?);
+        }
+        Ok(expenses)
+    }
+}
@@ -0,0 +1,137 @@
+use super::journal::to_json;
+use super::{DatabaseError, DatabaseService, ExpenseHistoryEntry, HistoryChange};
+use crate::request::types::{SessionContext, args::AddExpenseArgs};
+use libsql::{Connection, params};
+use serde_json::{Map, Value as JsonValue, json};
+
+// Columns shown in the history, bookkeeping like message ids is left out
+const HISTORY_COLUMNS: &[&str] = &[
+    "amount_minor",
+    "currency",
+    "description",
+    "category",
+    "expense_date",
+    "deleted_at",
+    "account",
+];
+
+fn tracked_values(state: &Map<String, JsonValue>) -> Map<String, JsonValue> {
+    state
+        .iter()
+        .filter(|(column, _)| HISTORY_COLUMNS.contains(&column.as_str()))
+        .map(|(column, value)| (column.clone(), value.clone()))
+        .collect()
+}
+
+// Tracked values of an expense about to be inserted
+pub(super) fn new_expense_values(args: &AddExpenseArgs, account: &str) -> JsonValue {
+    json!({
+        "amount_minor": args.amount.minor(),
+        "currency": args.amount.currency().code(),
+        "description": args.description,
+        "category": args.category,
+        "expense_date": args.date.to_string(),
+        "deleted_at": null,
+        "account": account,
+    })
+}
+
+// Tracked columns of an expense including deleted ones
+pub(super) async fn expense_values(
+    conn: &Connection,
+    expense_id: i64,
+) -> Result<Option<Map<String, JsonValue>>, DatabaseError> {
+    let stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM expenses WHERE id = ?",
+            HISTORY_COLUMNS.join(", ")
+        ))
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+    let mut rows = stmt
+        .query(params![expense_id])
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+    let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    let mut values = Map::new();
+    for (idx, column) in HISTORY_COLUMNS.iter().enumerate() {
+        let value = row
+            .get_value(idx as i32)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        values.insert(column.to_string(), to_json(value));
+    }
+    Ok(Some(values))
+}
+
+// Append a history entry with the given previous state and the expense as it is now,
+// on the connection or transaction that made the change
+pub(super) async fn insert_expense_history(
+    conn: &Connection,
+    expense_id: i64,
+    ctx: &SessionContext,
+    change: HistoryChange,
+    previous_state: Option<&Map<String, JsonValue>>,
+    tool_name: &str,
+) -> Result<(), DatabaseError> {
+    let old_values = previous_state.map(tracked_values);
+    let new_values = expense_values(conn, expense_id).await?;
+    conn.execute(
+        "INSERT INTO expense_history (expense_id, user_id, change, old_values, new_values, source_message_id, tool_name, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+        params![
+            expense_id,
+            ctx.user_id,
+            change.as_str(),
+            old_values.map(|values| JsonValue::Object(values).to_string()),
+            new_values.map(|values| JsonValue::Object(values).to_string()),
+            ctx.user_message_id,
+            tool_name
+        ],
+    )
+    .await
+    .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    Ok(())
+}
+
+impl DatabaseService {
+    // Get the change history of an expense, oldest first
+    pub async fn get_expense_history(
+        &self,
+        user_id: i64,
+        expense_id: i64,
+    ) -> Result<Vec<ExpenseHistoryEntry>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, expense_id, change, old_values, new_values, source_message_id, tool_name, created_at
+                 FROM expense_history
+                 WHERE user_id = ? AND expense_id = ?
+                 ORDER BY id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, expense_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut history = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            history.push(ExpenseHistoryEntry::from_row(&row)?);
+        }
+        Ok(history)
+    }
+}
@@ -0,0 +1,167 @@
+use super::journal::record_type_str;
+use super::{DatabaseError, DatabaseService, HistoryChange, ImportBatch, ImportCounts, history};
+use crate::request::types::{ActionType, SessionContext};
+use libsql::{Transaction, params};
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+
+// Statement rows already imported by a previous import are skipped
+async fn is_imported(tx: &Transaction, user_id: i64, hash: &str) -> Result<bool, DatabaseError> {
+    let mut rows = tx
+        .query(
+            "SELECT 1 FROM imported_transactions WHERE user_id = ? AND hash = ?",
+            params![user_id, hash],
+        )
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    Ok(rows
+        .next()
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        .is_some())
+}
+
+async fn mark_imported(
+    tx: &Transaction,
+    user_id: i64,
+    hash: &str,
+    record_type: &ActionType,
+    record_id: i64,
+) -> Result<(), DatabaseError> {
+    tx.execute(
+        "INSERT INTO imported_transactions (user_id, hash, record_type, record_id, created_at)
+         VALUES (?, ?, ?, ?, datetime('now'))",
+        params![user_id, hash, record_type_str(record_type), record_id],
+    )
+    .await
+    .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    Ok(())
+}
+
+impl DatabaseService {
+    // Hashes of every statement row the user has imported
+    pub async fn get_import_hashes(&self, user_id: i64) -> Result<HashSet<String>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare("SELECT hash FROM imported_transactions WHERE user_id = ?")
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut hashes = HashSet::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            hashes.insert(
+                row.get(0)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            );
+        }
+        Ok(hashes)
+    }
+
+    // Inserts all rows of an import in one transaction. Imports are not journalled,
+    // each expense gets a history entry instead.
+    pub async fn import_batch(
+        &self,
+        batch: &ImportBatch,
+        ctx: &SessionContext,
+        tool_name: &str,
+    ) -> Result<ImportCounts, DatabaseError> {
+        // Accounts are resolved up front, an unknown account fails the whole import
+        let mut accounts = HashMap::new();
+        let requested = batch
+            .expenses
+            .iter()
+            .map(|e| e.args.account.clone())
+            .chain(batch.income.iter().map(|i| i.args.account.clone()));
+        for account in requested {
+            if let Entry::Vacant(entry) = accounts.entry(account) {
+                let name = self
+                    .account_name(ctx.user_id, entry.key().as_deref())
+                    .await?;
+                entry.insert(name);
+            }
+        }
+
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let mut counts = ImportCounts::default();
+
+        for entry in &batch.expenses {
+            if let Some(hash) = &entry.import_hash
+                && is_imported(&tx, ctx.user_id, hash).await?
+            {
+                counts.duplicates += 1;
+                continue;
+            }
+            let args = &entry.args;
+            let account = &accounts[&args.account];
+            tx.execute(
+                "INSERT INTO expenses (user_id, amount_minor, currency, description, category, expense_date, user_message_id, account, member_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+                params![ctx.user_id, args.amount.minor(), args.amount.currency().code(), args.description.to_string(), args.category.to_string(), args.date.to_string(), ctx.user_message_id, account.as_str(), ctx.member_id],
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            let expense_id = tx.last_insert_rowid();
+            tx.execute(
+                "INSERT INTO expense_history (expense_id, user_id, change, old_values, new_values, source_message_id, tool_name, created_at)
+                 VALUES (?, ?, ?, NULL, ?, ?, ?, datetime('now'))",
+                params![
+                    expense_id,
+                    ctx.user_id,
+                    HistoryChange::Create.as_str(),
+                    history::new_expense_values(args, account).to_string(),
+                    ctx.user_message_id,
+                    tool_name
+                ],
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            if let Some(hash) = &entry.import_hash {
+                mark_imported(&tx, ctx.user_id, hash, &ActionType::Expense, expense_id).await?;
+            }
+            counts.expenses += 1;
+        }
+
+        for entry in &batch.income {
+            if let Some(hash) = &entry.import_hash
+                && is_imported(&tx, ctx.user_id, hash).await?
+            {
+                counts.duplicates += 1;
+                continue;
+            }
+            let args = &entry.args;
+            tx.execute(
+                "INSERT INTO income (user_id, amount_minor, currency, source, category, income_date, account, user_message_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+                params![ctx.user_id, args.amount.minor(), args.amount.currency().code(), args.source.as_str(), args.category.as_str(), args.date.to_string(), accounts[&args.account].as_str(), ctx.user_message_id],
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            let income_id = tx.last_insert_rowid();
+            if let Some(hash) = &entry.import_hash {
+                mark_imported(&tx, ctx.user_id, hash, &ActionType::Income, income_id).await?;
+            }
+            counts.income += 1;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        // New categories and items, reload on next lookup
+        self.category_cache.remove(&ctx.user_id);
+        self.item_category_cache.remove(&ctx.user_id);
+        Ok(counts)
+    }
+}
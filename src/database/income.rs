@@ -0,0 +1,299 @@
+use super::{
+    CategorySummary, DatabaseError, DatabaseService, Income, JournalAction, MonthlyIncomeExpense,
+};
+use crate::request::types::{
+    ActionType, SessionContext,
+    args::{AddIncomeArgs, ModifyIncomeArgs},
+};
+use chrono::NaiveDate;
+use libsql::params;
+
+const INCOME_SELECT: &str = "SELECT id, user_id, amount_minor, currency, source, category, income_date, account, user_message_id, bot_message_id, created_at
+     FROM income";
+
+impl DatabaseService {
+    pub async fn add_income(
+        &self,
+        args: &AddIncomeArgs,
+        ctx: &SessionContext,
+    ) -> Result<i64, DatabaseError> {
+        let account = self
+            .account_name(ctx.user_id, args.account.as_deref())
+            .await?;
+        let income_id = self
+            .execute_returning_id(
+                "INSERT INTO income (user_id, amount_minor, currency, source, category, income_date, account, user_message_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+                params![
+                    ctx.user_id,
+                    args.amount.minor(),
+                    args.amount.currency().code(),
+                    args.source.as_str(),
+                    args.category.as_str(),
+                    args.date.to_string(),
+                    account,
+                    ctx.user_message_id
+                ],
+            )
+            .await?;
+        self.record_action(
+            ctx,
+            JournalAction::Create,
+            &ActionType::Income,
+            income_id,
+            None,
+        )
+        .await?;
+        Ok(income_id)
+    }
+
+    pub async fn modify_income(
+        &self,
+        args: ModifyIncomeArgs,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let mut set_clauses = Vec::new();
+        let mut values: Vec<libsql::Value> = Vec::new();
+
+        if let Some(amt) = args.amount {
+            set_clauses.push("amount_minor = ?");
+            values.push(amt.minor().into());
+        }
+        if let Some(source) = args.source {
+            set_clauses.push("source = ?");
+            values.push(source.into());
+        }
+        if let Some(category) = args.category {
+            set_clauses.push("category = ?");
+            values.push(category.into());
+        }
+        if let Some(d) = args.date {
+            set_clauses.push("income_date = ?");
+            values.push(d.to_string().into());
+        }
+        if let Some(account) = &args.account {
+            set_clauses.push("account = ?");
+            values.push(self.account_name(ctx.user_id, Some(account)).await?.into());
+        }
+
+        if set_clauses.is_empty() {
+            return Err(DatabaseError::QueryError("No fields to update".to_string()));
+        }
+
+        let previous_state = self
+            .snapshot_record(&ActionType::Income, ctx.user_id, args.income_id)
+            .await?;
+        let sql = format!(
+            "UPDATE income SET {} WHERE id = ? AND user_id = ?",
+            set_clauses.join(", ")
+        );
+        values.push(args.income_id.into());
+        values.push(ctx.user_id.into());
+        self.execute(&sql, libsql::params::Params::Positional(values))
+            .await?;
+        self.journal_change(
+            ctx,
+            JournalAction::Modify,
+            &ActionType::Income,
+            args.income_id,
+            previous_state,
+        )
+        .await
+    }
+
+    pub async fn delete_income(
+        &self,
+        income_id: i64,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let previous_state = self
+            .snapshot_record(&ActionType::Income, ctx.user_id, income_id)
+            .await?;
+        self.execute(
+            "DELETE FROM income WHERE id = ? AND user_id = ?",
+            params![income_id, ctx.user_id],
+        )
+        .await?;
+        self.journal_change(
+            ctx,
+            JournalAction::Delete,
+            &ActionType::Income,
+            income_id,
+            previous_state,
+        )
+        .await
+    }
+
+    pub async fn update_income_bot_message(
+        &self,
+        income_id: i64,
+        bot_message_id: i64,
+    ) -> Result<(), DatabaseError> {
+        self.execute(
+            "UPDATE income SET bot_message_id = ? WHERE id = ?",
+            params![bot_message_id, income_id],
+        )
+        .await
+    }
+
+    pub async fn get_income(
+        &self,
+        user_id: i64,
+        income_id: i64,
+    ) -> Result<Option<Income>, DatabaseError> {
+        Ok(self
+            .query_income(
+                &format!("{} WHERE user_id = ? AND id = ?", INCOME_SELECT),
+                params![user_id, income_id],
+            )
+            .await?
+            .pop())
+    }
+
+    pub async fn find_income_by_message(
+        &self,
+        user_id: i64,
+        message_id: i64,
+    ) -> Result<Vec<Income>, DatabaseError> {
+        self.query_income(
+            &format!(
+                "{} WHERE user_id = ? AND (user_message_id = ? OR bot_message_id = ?) ORDER BY id",
+                INCOME_SELECT
+            ),
+            params![user_id, message_id, message_id],
+        )
+        .await
+    }
+
+    // All income in date range, oldest first
+    pub async fn get_income_entries(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<Income>, DatabaseError> {
+        self.query_income(
+            &format!(
+                "{} WHERE user_id = ? AND income_date BETWEEN ? AND ? ORDER BY income_date, id",
+                INCOME_SELECT
+            ),
+            params![user_id, start_date.to_string(), end_date.to_string()],
+        )
+        .await
+    }
+
+    async fn query_income(
+        &self,
+        sql: &str,
+        params: impl libsql::params::IntoParams,
+    ) -> Result<Vec<Income>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(sql)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut income = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            income.push(Income::from_row(&row)?);
+        }
+        Ok(income)
+    }
+
+    // Income by category for date range, largest first
+    pub async fn get_income_breakdown(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<CategorySummary>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT category, SUM(amount_minor) as total
+                 FROM income
+                 WHERE user_id = ? AND income_date BETWEEN ? AND ?
+                 GROUP BY category
+                 ORDER BY total DESC",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut summaries = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            summaries.push(CategorySummary::from_row(&row)?);
+        }
+        Ok(summaries)
+    }
+
+    // Income and expense totals per month for date range, months with neither are omitted
+    pub async fn get_monthly_income_expense(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<MonthlyIncomeExpense>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT month, SUM(income), SUM(expenses) FROM (
+                    SELECT substr(income_date, 1, 7) || '-01' AS month, amount_minor AS income, 0 AS expenses
+                    FROM income
+                    WHERE user_id = ? AND income_date BETWEEN ? AND ?
+                    UNION ALL
+                    SELECT substr(expense_date, 1, 7) || '-01', 0, amount_minor
+                    FROM expenses
+                    WHERE user_id = ? AND deleted_at IS NULL AND expense_date BETWEEN ? AND ?
+                 )
+                 GROUP BY month
+                 ORDER BY month",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let (start, end) = (start_date.to_string(), end_date.to_string());
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start.as_str(),
+                end.as_str(),
+                user_id,
+                start.as_str(),
+                end.as_str()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut months = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            months.push(MonthlyIncomeExpense::from_row(&row)?);
+        }
+        Ok(months)
+    }
+}
@@ -0,0 +1,453 @@
+use super::history;
+use super::{DatabaseError, DatabaseService, HistoryChange, JournalAction, UndoneAction};
+use crate::request::types::{ActionType, RecordContext, SessionContext};
+use libsql::{Connection, Value, params};
+use serde_json::{Map, Value as JsonValue};
+
+// Columns restored when undoing, everything except the primary key
+const EXPENSE_COLUMNS: &[&str] = &[
+    "user_id",
+    "amount_minor",
+    "currency",
+    "description",
+    "category",
+    "expense_date",
+    "user_message_id",
+    "bot_message_id",
+    "created_at",
+    "deleted_at",
+    "account",
+    "member_id",
+];
+const CASH_COLUMNS: &[&str] = &[
+    "user_id",
+    "amount_minor",
+    "currency",
+    "transaction_date",
+    "user_message_id",
+    "bot_message_id",
+    "created_at",
+    "account",
+];
+const INCOME_COLUMNS: &[&str] = &[
+    "user_id",
+    "amount_minor",
+    "currency",
+    "source",
+    "category",
+    "income_date",
+    "account",
+    "user_message_id",
+    "bot_message_id",
+    "created_at",
+];
+const SETTLEMENT_COLUMNS: &[&str] = &[
+    "user_id",
+    "person",
+    "amount_minor",
+    "currency",
+    "settlement_date",
+    "account",
+    "user_message_id",
+    "bot_message_id",
+    "created_at",
+];
+const TRANSFER_COLUMNS: &[&str] = &[
+    "user_id",
+    "from_account",
+    "to_account",
+    "amount_minor",
+    "currency",
+    "transfer_date",
+    "user_message_id",
+    "bot_message_id",
+    "created_at",
+];
+
+struct JournalEntry {
+    id: i64,
+    action: JournalAction,
+    record_type: ActionType,
+    record_id: i64,
+    previous_state: Option<Map<String, JsonValue>>,
+}
+
+// Table, columns and the condition for a record that has not been deleted
+fn record_table(record_type: &ActionType) -> (&'static str, &'static [&'static str], &'static str) {
+    match record_type {
+        ActionType::Expense => ("expenses", EXPENSE_COLUMNS, "deleted_at IS NULL"),
+        ActionType::CashTransaction => ("cash_transactions", CASH_COLUMNS, "1 = 1"),
+        ActionType::Transfer => ("transfers", TRANSFER_COLUMNS, "1 = 1"),
+        ActionType::Income => ("income", INCOME_COLUMNS, "1 = 1"),
+        ActionType::Settlement => ("settlements", SETTLEMENT_COLUMNS, "1 = 1"),
+    }
+}
+
+pub(super) fn record_type_str(record_type: &ActionType) -> &'static str {
+    match record_type {
+        ActionType::Expense => "expense",
+        ActionType::CashTransaction => "cash",
+        ActionType::Transfer => "transfer",
+        ActionType::Income => "income",
+        ActionType::Settlement => "settlement",
+    }
+}
+
+fn parse_record_type(s: &str) -> Option<ActionType> {
+    match s {
+        "expense" => Some(ActionType::Expense),
+        "cash" => Some(ActionType::CashTransaction),
+        "transfer" => Some(ActionType::Transfer),
+        "income" => Some(ActionType::Income),
+        "settlement" => Some(ActionType::Settlement),
+        _ => None,
+    }
+}
+
+pub(super) fn to_json(value: Value) -> JsonValue {
+    match value {
+        Value::Integer(i) => i.into(),
+        Value::Real(f) => f.into(),
+        Value::Text(s) => s.into(),
+        Value::Null | Value::Blob(_) => JsonValue::Null,
+    }
+}
+
+fn from_json(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(s) => Value::Text(s.clone()),
+        _ => Value::Null,
+    }
+}
+
+// Current row as a column -> value map, None if the user has no such record
+pub(super) async fn snapshot_record(
+    conn: &Connection,
+    record_type: &ActionType,
+    user_id: i64,
+    record_id: i64,
+) -> Result<Option<Map<String, JsonValue>>, DatabaseError> {
+    let (table, columns, live) = record_table(record_type);
+    let stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM {} WHERE id = ? AND user_id = ? AND {}",
+            columns.join(", "),
+            table,
+            live
+        ))
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+    let mut rows = stmt
+        .query(params![record_id, user_id])
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+    let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    let mut snapshot = Map::new();
+    for (idx, column) in columns.iter().enumerate() {
+        let value = row
+            .get_value(idx as i32)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        snapshot.insert(column.to_string(), to_json(value));
+    }
+    Ok(Some(snapshot))
+}
+
+// Journal an action on the connection or transaction that made the change
+pub(super) async fn insert_journal_entry(
+    conn: &Connection,
+    ctx: &SessionContext,
+    action: JournalAction,
+    record_type: &ActionType,
+    record_id: i64,
+    previous_state: Option<Map<String, JsonValue>>,
+) -> Result<(), DatabaseError> {
+    conn.execute(
+        "INSERT INTO action_journal (user_id, member_id, action, record_type, record_id, previous_state, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, datetime('now'))",
+        params![
+            ctx.user_id,
+            ctx.member_id,
+            action.as_str(),
+            record_type_str(record_type),
+            record_id,
+            previous_state.map(|state| JsonValue::Object(state).to_string())
+        ],
+    )
+    .await
+    .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    Ok(())
+}
+
+impl DatabaseService {
+    pub(super) async fn snapshot_record(
+        &self,
+        record_type: &ActionType,
+        user_id: i64,
+        record_id: i64,
+    ) -> Result<Option<Map<String, JsonValue>>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        snapshot_record(&conn, record_type, user_id, record_id).await
+    }
+
+    pub(super) async fn record_action(
+        &self,
+        ctx: &SessionContext,
+        action: JournalAction,
+        record_type: &ActionType,
+        record_id: i64,
+        previous_state: Option<Map<String, JsonValue>>,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.get_connection().await?;
+        insert_journal_entry(&conn, ctx, action, record_type, record_id, previous_state).await
+    }
+
+    // Journal a modify or delete, skipped when the record did not exist
+    pub(super) async fn journal_change(
+        &self,
+        ctx: &SessionContext,
+        action: JournalAction,
+        record_type: &ActionType,
+        record_id: i64,
+        previous_state: Option<Map<String, JsonValue>>,
+    ) -> Result<(), DatabaseError> {
+        match previous_state {
+            Some(state) => {
+                self.record_action(ctx, action, record_type, record_id, Some(state))
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Reverse the user's most recent action that has not been undone yet. In a group
+    // chat only the member's own actions are undone.
+    // Returns None when there is nothing left to undo.
+    pub async fn undo_last_action(
+        &self,
+        ctx: &SessionContext,
+    ) -> Result<Option<UndoneAction>, DatabaseError> {
+        let user_id = ctx.user_id;
+        loop {
+            let Some(JournalEntry {
+                id: journal_id,
+                action,
+                record_type,
+                record_id,
+                previous_state,
+            }) = self.last_journal_entry(ctx).await?
+            else {
+                return Ok(None);
+            };
+            let (table, columns, _) = record_table(&record_type);
+            let is_expense = matches!(record_type, ActionType::Expense);
+            // A created record is shown as it was before being removed
+            let created = match action {
+                JournalAction::Create => self.get_record(&record_type, user_id, record_id).await?,
+                JournalAction::Modify | JournalAction::Delete => None,
+            };
+
+            // The row, the journal entry and the history change together, so a failed
+            // undo leaves the entry open and nothing reverted
+            {
+                let conn = self.get_connection().await?;
+                let tx = conn
+                    .transaction()
+                    .await
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+                let expense_before = if is_expense {
+                    history::expense_values(&tx, record_id).await?
+                } else {
+                    None
+                };
+                match action {
+                    JournalAction::Create => {
+                        let sql = if is_expense {
+                            "UPDATE expenses SET deleted_at = datetime('now') WHERE id = ? AND user_id = ? AND deleted_at IS NULL"
+                                .to_string()
+                        } else {
+                            format!("DELETE FROM {} WHERE id = ? AND user_id = ?", table)
+                        };
+                        tx.execute(&sql, params![record_id, user_id])
+                            .await
+                            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+                    }
+                    JournalAction::Modify | JournalAction::Delete => {
+                        let previous_state = previous_state.unwrap_or_default();
+                        let mut values: Vec<Value> = columns
+                            .iter()
+                            .map(|column| {
+                                previous_state
+                                    .get(*column)
+                                    .map(from_json)
+                                    .unwrap_or(Value::Null)
+                            })
+                            .collect();
+                        values.push(record_id.into());
+                        // Replacing the row covers both a modified and a deleted record
+                        tx.execute(
+                            &format!(
+                                "INSERT OR REPLACE INTO {} ({}, id) VALUES ({}?)",
+                                table,
+                                columns.join(", "),
+                                "?, ".repeat(columns.len())
+                            ),
+                            libsql::params::Params::Positional(values),
+                        )
+                        .await
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+                    }
+                }
+
+                tx.execute(
+                    "UPDATE action_journal SET undone_at = datetime('now') WHERE id = ?",
+                    params![journal_id],
+                )
+                .await
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+                let live = match action {
+                    JournalAction::Create => created.is_some(),
+                    JournalAction::Modify | JournalAction::Delete => {
+                        snapshot_record(&tx, &record_type, user_id, record_id)
+                            .await?
+                            .is_some()
+                    }
+                };
+                if is_expense && live {
+                    let change = match action {
+                        JournalAction::Create => HistoryChange::Delete,
+                        JournalAction::Modify => HistoryChange::Modify,
+                        JournalAction::Delete => HistoryChange::Restore,
+                    };
+                    history::insert_expense_history(
+                        &tx,
+                        record_id,
+                        ctx,
+                        change,
+                        expense_before.as_ref(),
+                        "undo_last_action",
+                    )
+                    .await?;
+                }
+                tx.commit()
+                    .await
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            }
+            let record = match action {
+                JournalAction::Create => created,
+                JournalAction::Modify | JournalAction::Delete => {
+                    self.get_record(&record_type, user_id, record_id).await?
+                }
+            };
+            // Caches may hold categories of the undone record
+            self.category_cache.remove(&user_id);
+            self.item_category_cache.remove(&user_id);
+
+            // A record already gone has nothing to show, move on to the previous action
+            if let Some(record) = record {
+                return Ok(Some(UndoneAction { action, record }));
+            }
+        }
+    }
+
+    // Actions journalled before members were recorded belong to the chat itself
+    async fn last_journal_entry(
+        &self,
+        ctx: &SessionContext,
+    ) -> Result<Option<JournalEntry>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, action, record_type, record_id, previous_state
+                 FROM action_journal
+                 WHERE user_id = ? AND COALESCE(member_id, user_id) = ? AND undone_at IS NULL
+                 ORDER BY id DESC
+                 LIMIT 1",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![ctx.user_id, ctx.acting_member()])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+        let text = |idx: i32| -> Result<String, DatabaseError> {
+            row.get(idx)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))
+        };
+        let action = text(1)?;
+        let record_type = text(2)?;
+        let previous_state: Option<String> = row
+            .get(4)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(Some(JournalEntry {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            action: JournalAction::parse(&action).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown journal action '{}'", action))
+            })?,
+            record_type: parse_record_type(&record_type).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown record type '{}'", record_type))
+            })?,
+            record_id: row
+                .get(3)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            previous_state: previous_state
+                .and_then(|state| serde_json::from_str::<JsonValue>(&state).ok())
+                .and_then(|state| match state {
+                    JsonValue::Object(map) => Some(map),
+                    _ => None,
+                }),
+        }))
+    }
+
+    async fn get_record(
+        &self,
+        record_type: &ActionType,
+        user_id: i64,
+        record_id: i64,
+    ) -> Result<Option<RecordContext>, DatabaseError> {
+        Ok(match record_type {
+            ActionType::Expense => self
+                .get_expense(user_id, record_id)
+                .await?
+                .map(RecordContext::Expense),
+            ActionType::CashTransaction => self
+                .get_cash_transaction(user_id, record_id)
+                .await?
+                .map(RecordContext::CashTransaction),
+            ActionType::Transfer => self
+                .get_transfer(user_id, record_id)
+                .await?
+                .map(RecordContext::Transfer),
+            ActionType::Income => self
+                .get_income(user_id, record_id)
+                .await?
+                .map(RecordContext::Income),
+            ActionType::Settlement => self
+                .get_settlement(user_id, record_id)
+                .await?
+                .map(RecordContext::Settlement),
+        })
+    }
+}
@@ -0,0 +1,128 @@
+use super::{CategorySummary, DatabaseError, DatabaseService};
+use crate::request::types::SessionContext;
+use chrono::NaiveDate;
+use libsql::params;
+
+// Shown for expenses added before members were recorded
+const UNATTRIBUTED_MEMBER: &str = "Unattributed";
+
+impl DatabaseService {
+    // Remember the display name of a chat member, only written when it changed
+    pub async fn record_member(
+        &self,
+        chat_id: i64,
+        member_id: i64,
+        name: &str,
+    ) -> Result<(), DatabaseError> {
+        let key = (chat_id, member_id);
+        if self.member_cache.get(&key).as_deref() == Some(name) {
+            return Ok(());
+        }
+        self.execute(
+            "INSERT INTO chat_members (chat_id, member_id, name, updated_at)
+             VALUES (?, ?, ?, datetime('now'))
+             ON CONFLICT (chat_id, member_id) DO UPDATE SET name = excluded.name, updated_at = excluded.updated_at",
+            params![chat_id, member_id, name],
+        )
+        .await?;
+        self.member_cache.insert(key, name.to_string());
+        Ok(())
+    }
+
+    pub async fn member_name(
+        &self,
+        chat_id: i64,
+        member_id: i64,
+    ) -> Result<Option<String>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare("SELECT name FROM chat_members WHERE chat_id = ? AND member_id = ?")
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![chat_id, member_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        match rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            Some(row) => Ok(Some(
+                row.get(0)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // Only the member who added an expense may change it. Expenses without a member
+    // and changes the bot makes on its own are allowed.
+    pub(super) async fn check_expense_owner(
+        &self,
+        ctx: &SessionContext,
+        expense_id: i64,
+    ) -> Result<(), DatabaseError> {
+        let Some(member_id) = ctx.member_id else {
+            return Ok(());
+        };
+        let Some(owner) = self
+            .get_expense(ctx.user_id, expense_id)
+            .await?
+            .and_then(|expense| expense.member_id)
+        else {
+            return Ok(());
+        };
+        if owner == member_id {
+            return Ok(());
+        }
+        let name = self
+            .member_name(ctx.user_id, owner)
+            .await?
+            .unwrap_or_else(|| "another member".to_string());
+        Err(DatabaseError::NotExpenseOwner(name))
+    }
+
+    // Spending per member for date range, the member name is in the category field
+    pub async fn get_member_breakdown(
+        &self,
+        chat_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<CategorySummary>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT COALESCE(m.name, ?), SUM(e.amount_minor) as total
+                 FROM expenses e
+                 LEFT JOIN chat_members m ON m.chat_id = e.user_id AND m.member_id = e.member_id
+                 WHERE e.user_id = ? AND e.deleted_at IS NULL AND e.expense_date BETWEEN ? AND ?
+                 GROUP BY e.member_id
+                 ORDER BY total DESC",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                UNATTRIBUTED_MEMBER,
+                chat_id,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut summaries = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            summaries.push(CategorySummary::from_row(&row)?);
+        }
+        Ok(summaries)
+    }
+}
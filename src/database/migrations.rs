@@ -0,0 +1,140 @@
+use super::DatabaseError;
+use libsql::{Connection, params};
+use tracing::info;
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+// Append new migrations at the end, never edit or reorder applied ones
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "iso_dates",
+        sql: include_str!("migrations/0002_iso_dates.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "money_minor_units",
+        sql: include_str!("migrations/0003_money_minor_units.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "budgets",
+        sql: include_str!("migrations/0004_budgets.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "recurring_rules",
+        sql: include_str!("migrations/0005_recurring_rules.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "action_journal",
+        sql: include_str!("migrations/0006_action_journal.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "expense_history",
+        sql: include_str!("migrations/0007_expense_history.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "imported_transactions",
+        sql: include_str!("migrations/0008_imported_transactions.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "accounts",
+        sql: include_str!("migrations/0009_accounts.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "income",
+        sql: include_str!("migrations/0010_income.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "chat_members",
+        sql: include_str!("migrations/0011_chat_members.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "splits",
+        sql: include_str!("migrations/0012_splits.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "expense_search",
+        sql: include_str!("migrations/0013_expense_search.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "digests",
+        sql: include_str!("migrations/0014_digests.sql"),
+    },
+];
+
+// Apply all migrations newer than the current schema version, each in its own transaction
+pub async fn run(conn: &Connection) -> Result<(), DatabaseError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        (),
+    )
+    .await
+    .map_err(|e| DatabaseError::MigrationError(e.to_string()))?;
+
+    let current_version = current_version(conn).await?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current_version) {
+        info!(
+            version = migration.version,
+            name = migration.name,
+            "Applying database migration"
+        );
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::MigrationError(e.to_string()))?;
+        tx.execute_batch(migration.sql)
+            .await
+            .map_err(|e| DatabaseError::MigrationError(format!("{}: {}", migration.name, e)))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, datetime('now'))",
+            params![migration.version, migration.name],
+        )
+        .await
+        .map_err(|e| DatabaseError::MigrationError(e.to_string()))?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::MigrationError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+async fn current_version(conn: &Connection) -> Result<i64, DatabaseError> {
+    let mut rows = conn
+        .query("SELECT COALESCE(MAX(version), 0) FROM schema_version", ())
+        .await
+        .map_err(|e| DatabaseError::MigrationError(e.to_string()))?;
+    match rows
+        .next()
+        .await
+        .map_err(|e| DatabaseError::MigrationError(e.to_string()))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| DatabaseError::MigrationError(e.to_string())),
+        None => Ok(0),
+    }
+}
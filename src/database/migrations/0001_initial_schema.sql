@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS expenses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    category TEXT NOT NULL,
+    expense_date TEXT NOT NULL,
+    user_message_id INTEGER NOT NULL,
+    bot_message_id INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS cash_transactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    transaction_date TEXT NOT NULL,
+    user_message_id INTEGER NOT NULL,
+    bot_message_id INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_expenses_user_messages
+    ON expenses (user_id, user_message_id, bot_message_id);
+
+CREATE INDEX IF NOT EXISTS idx_cash_transactions_user_messages
+    ON cash_transactions (user_id, user_message_id, bot_message_id);
@@ -0,0 +1,40 @@
+-- Convert dd/mm/yyyy (and d.m.yy style) date strings to ISO-8601 yyyy-mm-dd
+
+UPDATE expenses SET expense_date = replace(expense_date, '.', '/')
+WHERE expense_date LIKE '%.%.%';
+
+UPDATE expenses SET expense_date = (
+    SELECT printf('%04d-%02d-%02d', CASE WHEN y < 100 THEN y + 2000 ELSE y END, m, d)
+    FROM (
+        SELECT
+            CAST(substr(expense_date, 1, instr(expense_date, '/') - 1) AS INTEGER) AS d,
+            CAST(substr(rest, 1, instr(rest, '/') - 1) AS INTEGER) AS m,
+            CAST(substr(rest, instr(rest, '/') + 1) AS INTEGER) AS y
+        FROM (SELECT substr(expense_date, instr(expense_date, '/') + 1) AS rest)
+    )
+)
+WHERE expense_date LIKE '%/%/%';
+
+UPDATE cash_transactions SET transaction_date = replace(transaction_date, '.', '/')
+WHERE transaction_date LIKE '%.%.%';
+
+UPDATE cash_transactions SET transaction_date = (
+    SELECT printf('%04d-%02d-%02d', CASE WHEN y < 100 THEN y + 2000 ELSE y END, m, d)
+    FROM (
+        SELECT
+            CAST(substr(transaction_date, 1, instr(transaction_date, '/') - 1) AS INTEGER) AS d,
+            CAST(substr(rest, 1, instr(rest, '/') - 1) AS INTEGER) AS m,
+            CAST(substr(rest, instr(rest, '/') + 1) AS INTEGER) AS y
+        FROM (SELECT substr(transaction_date, instr(transaction_date, '/') + 1) AS rest)
+    )
+)
+WHERE transaction_date LIKE '%/%/%';
+
+CREATE INDEX IF NOT EXISTS idx_expenses_user_date
+    ON expenses (user_id, expense_date);
+
+CREATE INDEX IF NOT EXISTS idx_expenses_user_category_date
+    ON expenses (user_id, category, expense_date);
+
+CREATE INDEX IF NOT EXISTS idx_cash_transactions_user_date
+    ON cash_transactions (user_id, transaction_date);
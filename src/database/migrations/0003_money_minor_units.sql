@@ -0,0 +1,9 @@
+-- Store amounts as integer minor units (paise) with an explicit currency
+
+ALTER TABLE expenses RENAME COLUMN amount TO amount_minor;
+UPDATE expenses SET amount_minor = CAST(ROUND(amount_minor * 100) AS INTEGER);
+ALTER TABLE expenses ADD COLUMN currency TEXT NOT NULL DEFAULT 'INR';
+
+ALTER TABLE cash_transactions RENAME COLUMN amount TO amount_minor;
+UPDATE cash_transactions SET amount_minor = CAST(ROUND(amount_minor * 100) AS INTEGER);
+ALTER TABLE cash_transactions ADD COLUMN currency TEXT NOT NULL DEFAULT 'INR';
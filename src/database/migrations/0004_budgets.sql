@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS budgets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    category TEXT NOT NULL COLLATE NOCASE,
+    amount_minor INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'INR',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (user_id, category)
+);
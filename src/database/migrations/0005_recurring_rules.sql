@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS recurring_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    amount_minor INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'INR',
+    description TEXT,
+    category TEXT,
+    frequency TEXT NOT NULL,
+    anchor_day INTEGER NOT NULL,
+    next_run_date TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active',
+    user_message_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_recurring_rules_due
+    ON recurring_rules (status, next_run_date);
+
+CREATE INDEX IF NOT EXISTS idx_recurring_rules_user
+    ON recurring_rules (user_id, status);
@@ -0,0 +1,15 @@
+-- Every create/modify/delete of an expense or cash transaction, newest last,
+-- with the row as it was before the change so the action can be undone
+CREATE TABLE IF NOT EXISTS action_journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    record_type TEXT NOT NULL,
+    record_id INTEGER NOT NULL,
+    previous_state TEXT,
+    undone_at TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_action_journal_user
+    ON action_journal (user_id, undone_at, id);
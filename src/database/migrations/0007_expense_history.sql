@@ -0,0 +1,19 @@
+-- Deleted expenses are kept and hidden from every query
+ALTER TABLE expenses ADD COLUMN deleted_at TEXT;
+
+-- Append-only log of every change to an expense, values are JSON objects of the
+-- tracked columns before and after the change
+CREATE TABLE IF NOT EXISTS expense_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    expense_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    change TEXT NOT NULL,
+    old_values TEXT,
+    new_values TEXT,
+    source_message_id INTEGER NOT NULL,
+    tool_name TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_expense_history_expense
+    ON expense_history (expense_id, id);
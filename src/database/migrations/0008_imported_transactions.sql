@@ -0,0 +1,10 @@
+-- Hashes of imported bank statement rows, so re-importing an overlapping statement
+-- skips rows that were already added even if the record was later deleted
+CREATE TABLE IF NOT EXISTS imported_transactions (
+    user_id INTEGER NOT NULL,
+    hash TEXT NOT NULL,
+    record_type TEXT NOT NULL,
+    record_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, hash)
+);
@@ -0,0 +1,33 @@
+-- Named accounts such as cash, bank, credit card or UPI wallet. Users without any
+-- account get a default "Cash" account, which is where all earlier entries belong.
+CREATE TABLE IF NOT EXISTS accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_accounts_user_name
+    ON accounts (user_id, name COLLATE NOCASE);
+
+ALTER TABLE expenses ADD COLUMN account TEXT NOT NULL DEFAULT 'Cash';
+ALTER TABLE cash_transactions ADD COLUMN account TEXT NOT NULL DEFAULT 'Cash';
+
+-- Money moved between two accounts, e.g. an ATM withdrawal from bank to cash
+CREATE TABLE IF NOT EXISTS transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    from_account TEXT NOT NULL,
+    to_account TEXT NOT NULL,
+    amount_minor INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'INR',
+    transfer_date TEXT NOT NULL,
+    user_message_id INTEGER NOT NULL,
+    bot_message_id INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfers_user_messages
+    ON transfers (user_id, user_message_id, bot_message_id);
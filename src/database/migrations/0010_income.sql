@@ -0,0 +1,21 @@
+-- Money earned such as salary, refunds or interest, kept apart from cash movements
+-- so it can be reported against expenses
+CREATE TABLE IF NOT EXISTS income (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    amount_minor INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'INR',
+    source TEXT NOT NULL,
+    category TEXT NOT NULL,
+    income_date TEXT NOT NULL,
+    account TEXT NOT NULL DEFAULT 'Cash',
+    user_message_id INTEGER NOT NULL,
+    bot_message_id INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_income_user_date
+    ON income (user_id, income_date);
+
+CREATE INDEX IF NOT EXISTS idx_income_user_messages
+    ON income (user_id, user_message_id, bot_message_id);
@@ -0,0 +1,13 @@
+-- A group chat shares one ledger keyed by the chat, so entries also record which
+-- member made them. NULL for entries made before groups were supported.
+ALTER TABLE expenses ADD COLUMN member_id INTEGER;
+ALTER TABLE action_journal ADD COLUMN member_id INTEGER;
+
+-- Display names of the members seen in each chat, used in per-member reports
+CREATE TABLE IF NOT EXISTS chat_members (
+    chat_id INTEGER NOT NULL,
+    member_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (chat_id, member_id)
+);
@@ -0,0 +1,38 @@
+-- Shares of an expense the user paid that other people owe. A share is the fraction
+-- weight / total_weight of the expense, so it follows later changes to its amount.
+CREATE TABLE IF NOT EXISTS expense_shares (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    expense_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    person TEXT NOT NULL,
+    weight INTEGER NOT NULL,
+    total_weight INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_expense_shares_expense
+    ON expense_shares (expense_id);
+
+CREATE INDEX IF NOT EXISTS idx_expense_shares_user_person
+    ON expense_shares (user_id, person);
+
+-- Money settled with a person, positive when they paid the user and negative when
+-- the user paid them
+CREATE TABLE IF NOT EXISTS settlements (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    person TEXT NOT NULL,
+    amount_minor INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'INR',
+    settlement_date TEXT NOT NULL,
+    account TEXT NOT NULL DEFAULT 'Cash',
+    user_message_id INTEGER NOT NULL,
+    bot_message_id INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlements_user_person
+    ON settlements (user_id, person);
+
+CREATE INDEX IF NOT EXISTS idx_settlements_user_messages
+    ON settlements (user_id, user_message_id, bot_message_id);
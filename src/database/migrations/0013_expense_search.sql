@@ -0,0 +1,22 @@
+-- Full-text index over expense descriptions, the rowid is the expense id. The index
+-- keeps its own copy of the text so an expense replaced on undo re-indexes cleanly.
+CREATE VIRTUAL TABLE IF NOT EXISTS expenses_fts USING fts5(
+    description,
+    tokenize = 'porter unicode61'
+);
+
+INSERT INTO expenses_fts (rowid, description)
+    SELECT id, description FROM expenses;
+
+CREATE TRIGGER IF NOT EXISTS expenses_fts_insert AFTER INSERT ON expenses BEGIN
+    DELETE FROM expenses_fts WHERE rowid = new.id;
+    INSERT INTO expenses_fts (rowid, description) VALUES (new.id, new.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS expenses_fts_update AFTER UPDATE OF description ON expenses BEGIN
+    UPDATE expenses_fts SET description = new.description WHERE rowid = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS expenses_fts_delete AFTER DELETE ON expenses BEGIN
+    DELETE FROM expenses_fts WHERE rowid = old.id;
+END;
@@ -0,0 +1,11 @@
+-- Opt-in spending digests, one per user. next_run_date is the day the next digest is
+-- sent, covering the week or month that ended the day before.
+CREATE TABLE IF NOT EXISTS digest_subscriptions (
+    user_id INTEGER PRIMARY KEY,
+    frequency TEXT NOT NULL,
+    next_run_date TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_digest_subscriptions_next_run
+    ON digest_subscriptions (next_run_date);
@@ -1,16 +1,35 @@
+use chrono::{Datelike, NaiveDate};
 use libsql::{Builder, Connection, Database, params, params::IntoParams};
-use std::{env, time::Duration};
+use std::{collections::HashMap, env, ops::Deref, sync::Arc, time::Duration};
 use thiserror::Error;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 use crate::{
-    core::ExpirableCache,
-    request::types::{
-        SessionContext,
-        args::{AddCashArgs, AddExpenseArgs, ModifyExpenseArgs},
+    configuration::DatabaseConfig,
+    core::{Currency, ExpirableCache, Money},
+    request::{
+        description_item,
+        types::{
+            ActionType, RecordContext, SessionContext,
+            args::{
+                AddCashArgs, AddExpenseArgs, AddRecurringArgs, ModifyCashArgs, ModifyExpenseArgs,
+            },
+        },
     },
 };
+mod accounts;
+mod digests;
+mod history;
+mod import;
+mod income;
+mod journal;
+mod members;
+mod migrations;
+mod search;
+mod splits;
 mod types;
 
+pub use accounts::DEFAULT_ACCOUNT;
 pub use types::*;
 
 #[derive(Error, Debug, Clone)]
@@ -23,35 +42,174 @@ pub enum DatabaseError {
 
     #[error("Database query error: {0}")]
     QueryError(String),
+
+    #[error("TURSO_AUTH_TOKEN must be set for remote databases")]
+    MissingAuthToken,
+
+    #[error("Database migration error: {0}")]
+    MigrationError(String),
+
+    #[error("Unknown account: {0}")]
+    UnknownAccount(String),
+
+    #[error("Expense belongs to {0}")]
+    NotExpenseOwner(String),
+
+    #[error("Expense #{0} not found")]
+    ExpenseNotFound(i64),
+
+    #[error("Cash transaction #{0} not found")]
+    CashTransactionNotFound(i64),
+
+    #[error("Settlement #{0} not found")]
+    SettlementNotFound(i64),
 }
 
 const DEFAULT_CATEGORY_CACHE_TTL: u64 = 86400 * 30;
+const ITEM_CATEGORY_LOOKBACK: i64 = 1000;
 pub struct DatabaseService {
     pub db: Database,
+    shared_connection: Option<Arc<Mutex<Connection>>>,
     pub category_cache: ExpirableCache<i64, Vec<String>>,
+    // Most recent category used for each expense item, e.g. "fruits" -> "Grocery"
+    pub item_category_cache: ExpirableCache<i64, HashMap<String, String>>,
+    // Last stored display name of each (chat, member)
+    member_cache: ExpirableCache<(i64, i64), String>,
 }
 
 impl DatabaseService {
-    pub async fn new(db_url: String) -> Result<Self, DatabaseError> {
-        let url = db_url;
-        let token = env::var("TURSO_AUTH_TOKEN").expect("TURSO_AUTH_TOKEN must be set");
-
-        let db = Builder::new_remote(url, token)
-            .build()
-            .await
-            .map_err(|e| DatabaseError::DatabaseBuildError(e.to_string()))?;
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, DatabaseError> {
+        // In-memory databases exist per connection, so a single one is shared. It is
+        // locked while in use so tasks never interleave statements or transactions.
+        let mut shared_connection = None;
+        let db = match config {
+            DatabaseConfig::Local { path } => Builder::new_local(path)
+                .build()
+                .await
+                .map_err(|e| DatabaseError::DatabaseBuildError(e.to_string()))?,
+            DatabaseConfig::Memory => {
+                let db = Builder::new_local(":memory:")
+                    .build()
+                    .await
+                    .map_err(|e| DatabaseError::DatabaseBuildError(e.to_string()))?;
+                shared_connection =
+                    Some(Arc::new(Mutex::new(db.connect().map_err(|e| {
+                        DatabaseError::ConnectionError(e.to_string())
+                    })?)));
+                db
+            }
+            DatabaseConfig::Replica {
+                path,
+                url,
+                sync_interval_secs,
+            } => {
+                let mut builder =
+                    Builder::new_remote_replica(path, url.clone(), Self::auth_token()?);
+                if let Some(secs) = sync_interval_secs {
+                    builder = builder.sync_interval(Duration::from_secs(*secs));
+                }
+                let db = builder
+                    .build()
+                    .await
+                    .map_err(|e| DatabaseError::DatabaseBuildError(e.to_string()))?;
+                db.sync()
+                    .await
+                    .map_err(|e| DatabaseError::DatabaseBuildError(e.to_string()))?;
+                db
+            }
+            DatabaseConfig::Remote { url } => Builder::new_remote(url.clone(), Self::auth_token()?)
+                .build()
+                .await
+                .map_err(|e| DatabaseError::DatabaseBuildError(e.to_string()))?,
+        };
         let category_cache =
             ExpirableCache::new(10, Duration::from_secs(DEFAULT_CATEGORY_CACHE_TTL));
-        Ok(Self { db, category_cache })
+        let item_category_cache =
+            ExpirableCache::new(10, Duration::from_secs(DEFAULT_CATEGORY_CACHE_TTL));
+        let member_cache =
+            ExpirableCache::new(1000, Duration::from_secs(DEFAULT_CATEGORY_CACHE_TTL));
+        let service = Self {
+            db,
+            shared_connection,
+            category_cache,
+            item_category_cache,
+            member_cache,
+        };
+        migrations::run(&*service.get_connection().await?).await?;
+        Ok(service)
+    }
+
+    fn auth_token() -> Result<String, DatabaseError> {
+        env::var("TURSO_AUTH_TOKEN").map_err(|_| DatabaseError::MissingAuthToken)
     }
 
-    async fn get_connection(&self) -> Result<Connection, DatabaseError> {
+    // Holds the shared connection's lock until dropped, so never call another
+    // DatabaseService method while a connection is in scope
+    async fn get_connection(&self) -> Result<DbConnection, DatabaseError> {
+        if let Some(conn) = &self.shared_connection {
+            return Ok(DbConnection::Shared(conn.clone().lock_owned().await));
+        }
         self.db
             .connect()
+            .map(DbConnection::Owned)
             .map_err(|e| DatabaseError::ConnectionError(e.to_string()))
     }
 }
 
+enum DbConnection {
+    Owned(Connection),
+    Shared(OwnedMutexGuard<Connection>),
+}
+
+impl Deref for DbConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            DbConnection::Owned(conn) => conn,
+            DbConnection::Shared(conn) => conn,
+        }
+    }
+}
+
+// Insert an expense with its journal entry and history, on the transaction that also
+// writes anything else belonging to it
+async fn insert_expense(
+    conn: &Connection,
+    args: &AddExpenseArgs,
+    account: &str,
+    session_context: &SessionContext,
+    tool_name: &str,
+) -> Result<i64, DatabaseError> {
+    conn.execute(
+        "INSERT INTO expenses (user_id, amount_minor, currency, description, category, expense_date, user_message_id, account, member_id, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+        params![session_context.user_id, args.amount.minor(), args.amount.currency().code(), args.description.to_string(), args.category.to_string(), args.date.to_string(), session_context.user_message_id, account, session_context.member_id]
+    )
+    .await
+    .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    let expense_id = conn.last_insert_rowid();
+    journal::insert_journal_entry(
+        conn,
+        session_context,
+        JournalAction::Create,
+        &ActionType::Expense,
+        expense_id,
+        None,
+    )
+    .await?;
+    history::insert_expense_history(
+        conn,
+        expense_id,
+        session_context,
+        HistoryChange::Create,
+        None,
+        tool_name,
+    )
+    .await?;
+    Ok(expense_id)
+}
+
 impl DatabaseService {
     async fn execute_returning_id(
         &self,
@@ -65,6 +223,17 @@ impl DatabaseService {
         Ok(conn.last_insert_rowid())
     }
 
+    async fn execute_returning_count(
+        &self,
+        sql: &str,
+        params: impl IntoParams,
+    ) -> Result<u64, DatabaseError> {
+        let conn = self.get_connection().await?;
+        conn.execute(sql, params)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
     async fn execute(&self, sql: &str, params: impl IntoParams) -> Result<(), DatabaseError> {
         let conn = self.get_connection().await?;
         conn.execute(sql, params)
@@ -74,11 +243,32 @@ impl DatabaseService {
         Ok(())
     }
 
+    // tool_name records what made the change in the expense history
     pub async fn add_expense(
         &self,
         args: &AddExpenseArgs,
         session_context: &SessionContext,
+        tool_name: &str,
     ) -> Result<i64, DatabaseError> {
+        let account = self.prepare_expense(args, session_context).await?;
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let expense_id = insert_expense(&tx, args, &account, session_context, tool_name).await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(expense_id)
+    }
+
+    // Updates the category caches for a new expense, returns its stored account name
+    async fn prepare_expense(
+        &self,
+        args: &AddExpenseArgs,
+        session_context: &SessionContext,
+    ) -> Result<String, DatabaseError> {
         // Update cache if current category is a new category
         if let Some(mut cache) = self.category_cache.get(&session_context.user_id)
             && !cache.contains(&args.category)
@@ -87,12 +277,15 @@ impl DatabaseService {
             self.category_cache.remove(&session_context.user_id);
             self.category_cache.insert(session_context.user_id, cache);
         }
+        if let Some(mut cache) = self.item_category_cache.get(&session_context.user_id) {
+            cache.insert(description_item(&args.description), args.category.clone());
+            self.item_category_cache.remove(&session_context.user_id);
+            self.item_category_cache
+                .insert(session_context.user_id, cache);
+        }
 
-        self.execute_returning_id(
-            "INSERT INTO expenses (user_id, amount, description, category, expense_date, user_message_id, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, datetime('now'))", 
-            params![session_context.user_id, args.amount, args.description.to_string(), args.category.to_string(), args.date.to_string(), session_context.user_message_id]
-        ).await
+        self.account_name(session_context.user_id, args.account.as_deref())
+            .await
     }
 
     pub async fn update_expense_bot_message(
@@ -113,12 +306,24 @@ impl DatabaseService {
         args: &AddCashArgs,
         session_context: &SessionContext,
     ) -> Result<i64, DatabaseError> {
-        self.execute_returning_id(
-            "INSERT INTO cash_transactions (user_id, amount, transaction_date, user_message_id, created_at)
-             VALUES (?, ?, ?, ?, datetime('now'))",
-            params![session_context.user_id, args.amount, args.date.to_string(), session_context.user_message_id],
+        let account = self
+            .account_name(session_context.user_id, args.account.as_deref())
+            .await?;
+        let cash_id = self.execute_returning_id(
+            "INSERT INTO cash_transactions (user_id, amount_minor, currency, transaction_date, user_message_id, account, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, datetime('now'))",
+            params![session_context.user_id, args.amount.minor(), args.amount.currency().code(), args.date.to_string(), session_context.user_message_id, account],
         )
-        .await
+        .await?;
+        self.record_action(
+            session_context,
+            JournalAction::Create,
+            &ActionType::CashTransaction,
+            cash_id,
+            None,
+        )
+        .await?;
+        Ok(cash_id)
     }
 
     // Update cash transaction with bot_message_id
@@ -139,7 +344,9 @@ impl DatabaseService {
         &self,
         args: ModifyExpenseArgs,
         ctx: &SessionContext,
+        tool_name: &str,
     ) -> Result<(), DatabaseError> {
+        self.check_expense_owner(ctx, args.expense_id).await?;
         // Update cache if there is a category and it is a new category
         if let Some(mut cache) = self.category_cache.get(&ctx.user_id)
             && let Some(category) = &args.category
@@ -149,13 +356,15 @@ impl DatabaseService {
             self.category_cache.remove(&ctx.user_id);
             self.category_cache.insert(ctx.user_id, cache);
         }
+        // Item or category may have changed, reload on next lookup
+        self.item_category_cache.remove(&ctx.user_id);
 
         let mut set_clauses = Vec::new();
         let mut values: Vec<libsql::Value> = Vec::new();
 
         if let Some(amt) = args.amount {
-            set_clauses.push("amount = ?");
-            values.push(amt.into());
+            set_clauses.push("amount_minor = ?");
+            values.push(amt.minor().into());
         }
         if let Some(desc) = args.description {
             set_clauses.push("description = ?");
@@ -167,51 +376,235 @@ impl DatabaseService {
         }
         if let Some(d) = args.date {
             set_clauses.push("expense_date = ?");
-            values.push(d.into());
+            values.push(d.to_string().into());
+        }
+        if let Some(account) = &args.account {
+            set_clauses.push("account = ?");
+            values.push(self.account_name(ctx.user_id, Some(account)).await?.into());
         }
 
         if set_clauses.is_empty() {
             return Err(DatabaseError::QueryError("No fields to update".to_string()));
         }
 
+        // The row, its journal entry and its history change together or not at all
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let Some(previous_state) =
+            journal::snapshot_record(&tx, &ActionType::Expense, ctx.user_id, args.expense_id)
+                .await?
+        else {
+            return Err(DatabaseError::ExpenseNotFound(args.expense_id));
+        };
         let sql = format!(
             "UPDATE expenses SET {} WHERE id = ? AND user_id = ?",
             set_clauses.join(", ")
         );
         values.push(args.expense_id.into());
         values.push(ctx.user_id.into());
-        self.execute(&sql, libsql::params::Params::Positional(values))
+        tx.execute(&sql, libsql::params::Params::Positional(values))
             .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        journal::insert_journal_entry(
+            &tx,
+            ctx,
+            JournalAction::Modify,
+            &ActionType::Expense,
+            args.expense_id,
+            Some(previous_state.clone()),
+        )
+        .await?;
+        history::insert_expense_history(
+            &tx,
+            args.expense_id,
+            ctx,
+            HistoryChange::Modify,
+            Some(&previous_state),
+            tool_name,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
     }
 
-    // Delete expense
+    // Soft delete expense, the row is kept for its history and undo
     pub async fn delete_expense(
         &self,
         expense_id: i64,
         ctx: &SessionContext,
+        tool_name: &str,
     ) -> Result<(), DatabaseError> {
-        self.execute(
-            "DELETE FROM expenses WHERE id = ? AND user_id = ?",
+        self.check_expense_owner(ctx, expense_id).await?;
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let Some(previous_state) =
+            journal::snapshot_record(&tx, &ActionType::Expense, ctx.user_id, expense_id).await?
+        else {
+            return Err(DatabaseError::ExpenseNotFound(expense_id));
+        };
+        tx.execute(
+            "UPDATE expenses SET deleted_at = datetime('now') WHERE id = ? AND user_id = ?",
             params![expense_id, ctx.user_id],
         )
         .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        journal::insert_journal_entry(
+            &tx,
+            ctx,
+            JournalAction::Delete,
+            &ActionType::Expense,
+            expense_id,
+            Some(previous_state.clone()),
+        )
+        .await?;
+        history::insert_expense_history(
+            &tx,
+            expense_id,
+            ctx,
+            HistoryChange::Delete,
+            Some(&previous_state),
+            tool_name,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        // Deleted categories and items should no longer be suggested
+        self.category_cache.remove(&ctx.user_id);
+        self.item_category_cache.remove(&ctx.user_id);
+        Ok(())
     }
 
-    // Get balance (cash added - expenses)
-    pub async fn get_balance(&self, user_id: i64) -> Result<i64, DatabaseError> {
+    // Modify cash transaction fields
+    pub async fn modify_cash_transaction(
+        &self,
+        args: ModifyCashArgs,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let mut set_clauses = Vec::new();
+        let mut values: Vec<libsql::Value> = Vec::new();
+
+        if let Some(amt) = args.amount {
+            set_clauses.push("amount_minor = ?");
+            values.push(amt.minor().into());
+        }
+        if let Some(d) = args.date {
+            set_clauses.push("transaction_date = ?");
+            values.push(d.to_string().into());
+        }
+        if let Some(account) = &args.account {
+            set_clauses.push("account = ?");
+            values.push(self.account_name(ctx.user_id, Some(account)).await?.into());
+        }
+
+        if set_clauses.is_empty() {
+            return Err(DatabaseError::QueryError("No fields to update".to_string()));
+        }
+
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let Some(previous_state) = journal::snapshot_record(
+            &tx,
+            &ActionType::CashTransaction,
+            ctx.user_id,
+            args.transaction_id,
+        )
+        .await?
+        else {
+            return Err(DatabaseError::CashTransactionNotFound(args.transaction_id));
+        };
+        let sql = format!(
+            "UPDATE cash_transactions SET {} WHERE id = ? AND user_id = ?",
+            set_clauses.join(", ")
+        );
+        values.push(args.transaction_id.into());
+        values.push(ctx.user_id.into());
+        tx.execute(&sql, libsql::params::Params::Positional(values))
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        journal::insert_journal_entry(
+            &tx,
+            ctx,
+            JournalAction::Modify,
+            &ActionType::CashTransaction,
+            args.transaction_id,
+            Some(previous_state),
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    // Delete cash transaction
+    pub async fn delete_cash_transaction(
+        &self,
+        transaction_id: i64,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let Some(previous_state) = journal::snapshot_record(
+            &tx,
+            &ActionType::CashTransaction,
+            ctx.user_id,
+            transaction_id,
+        )
+        .await?
+        else {
+            return Err(DatabaseError::CashTransactionNotFound(transaction_id));
+        };
+        tx.execute(
+            "DELETE FROM cash_transactions WHERE id = ? AND user_id = ?",
+            params![transaction_id, ctx.user_id],
+        )
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        journal::insert_journal_entry(
+            &tx,
+            ctx,
+            JournalAction::Delete,
+            &ActionType::CashTransaction,
+            transaction_id,
+            Some(previous_state),
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    // Get balance (cash added + income + settlements - expenses) over all accounts,
+    // transfers cancel out
+    pub async fn get_balance(&self, user_id: i64) -> Result<Money, DatabaseError> {
         let conn = self.get_connection().await?;
         let stmt = conn
             .prepare(
                 "SELECT
-                    (SELECT COALESCE(SUM(amount), 0) FROM cash_transactions WHERE user_id = ?) -
-                    (SELECT COALESCE(SUM(amount), 0) FROM expenses WHERE user_id = ?)
+                    (SELECT COALESCE(SUM(amount_minor), 0) FROM cash_transactions WHERE user_id = ?) +
+                    (SELECT COALESCE(SUM(amount_minor), 0) FROM income WHERE user_id = ?) +
+                    (SELECT COALESCE(SUM(amount_minor), 0) FROM settlements WHERE user_id = ?) -
+                    (SELECT COALESCE(SUM(amount_minor), 0) FROM expenses WHERE user_id = ? AND deleted_at IS NULL)
                  AS balance",
             )
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
         let mut rows = stmt
-            .query(params![user_id, user_id])
+            .query(params![user_id, user_id, user_id, user_id])
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
@@ -223,9 +616,9 @@ impl DatabaseService {
             let balance: i64 = row
                 .get(0)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
-            Ok(balance)
+            Ok(Money::from_minor(balance, Currency::default()))
         } else {
-            Ok(0)
+            Ok(Money::zero())
         }
     }
 
@@ -233,20 +626,15 @@ impl DatabaseService {
     pub async fn get_expense_breakdown(
         &self,
         user_id: i64,
-        start_date: &str,
-        end_date: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
     ) -> Result<Vec<CategorySummary>, DatabaseError> {
         let conn = self.get_connection().await?;
         let stmt = conn
             .prepare(
-                "SELECT category, SUM(amount) as total
+                "SELECT category, SUM(amount_minor) as total
                  FROM expenses
-                 WHERE user_id = ?
-                 AND substr(expense_date, 7, 4) || '-' || substr(expense_date, 4, 2) || '-' || substr(expense_date, 1, 2)
-                     BETWEEN
-                     substr(?, 7, 4) || '-' || substr(?, 4, 2) || '-' || substr(?, 1, 2)
-                     AND
-                     substr(?, 7, 4) || '-' || substr(?, 4, 2) || '-' || substr(?, 1, 2)
+                 WHERE user_id = ? AND deleted_at IS NULL AND expense_date BETWEEN ? AND ?
                  GROUP BY category
                  ORDER BY total DESC",
             )
@@ -255,7 +643,9 @@ impl DatabaseService {
 
         let mut rows = stmt
             .query(params![
-                user_id, start_date, start_date, start_date, end_date, end_date, end_date
+                user_id,
+                start_date.to_string(),
+                end_date.to_string()
             ])
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
@@ -271,33 +661,185 @@ impl DatabaseService {
         Ok(summaries)
     }
 
+    // Get total spending per day for date range, days without expenses are omitted
+    pub async fn get_daily_totals(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<DailyTotal>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT expense_date, SUM(amount_minor) as total
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL AND expense_date BETWEEN ? AND ?
+                 GROUP BY expense_date
+                 ORDER BY expense_date",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut totals = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            totals.push(DailyTotal::from_row(&row)?);
+        }
+        Ok(totals)
+    }
+
+    // Get category totals per month for date range
+    pub async fn get_monthly_category_totals(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<MonthlyCategorySummary>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT substr(expense_date, 1, 7) || '-01' as month, category, SUM(amount_minor) as total
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL AND expense_date BETWEEN ? AND ?
+                 GROUP BY month, category
+                 ORDER BY month, total DESC",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut summaries = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            summaries.push(MonthlyCategorySummary::from_row(&row)?);
+        }
+        Ok(summaries)
+    }
+
+    // Get all expenses in date range, oldest first
+    pub async fn get_expenses(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<Expense>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, amount_minor, description, category, expense_date, user_message_id, bot_message_id, created_at, currency, account, member_id
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL AND expense_date BETWEEN ? AND ?
+                 ORDER BY expense_date, id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut expenses = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            expenses.push(Expense::from_row(&row)?);
+        }
+        Ok(expenses)
+    }
+
+    // Get all cash transactions in date range, oldest first
+    pub async fn get_cash_transactions(
+        &self,
+        user_id: i64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<CashTransaction>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, amount_minor, transaction_date, user_message_id, bot_message_id, created_at, currency, account
+                 FROM cash_transactions
+                 WHERE user_id = ? AND transaction_date BETWEEN ? AND ?
+                 ORDER BY transaction_date, id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut transactions = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            transactions.push(CashTransaction::from_row(&row)?);
+        }
+        Ok(transactions)
+    }
+
     // Get expenses for specific category and date range
     pub async fn get_category_expenses(
         &self,
         user_id: i64,
         category: &str,
-        start_date: &str,
-        end_date: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
     ) -> Result<Vec<Expense>, DatabaseError> {
         let conn = self.get_connection().await?;
         let stmt = conn
             .prepare(
-                "SELECT id, user_id, amount, description, category, expense_date, user_message_id, bot_message_id, created_at
+                "SELECT id, user_id, amount_minor, description, category, expense_date, user_message_id, bot_message_id, created_at, currency, account, member_id
                  FROM expenses
-                 WHERE user_id = ? AND category = ?
-                 AND substr(expense_date, 7, 4) || '-' || substr(expense_date, 4, 2) || '-' || substr(expense_date, 1, 2)
-                     BETWEEN
-                     substr(?, 7, 4) || '-' || substr(?, 4, 2) || '-' || substr(?, 1, 2)
-                     AND
-                     substr(?, 7, 4) || '-' || substr(?, 4, 2) || '-' || substr(?, 1, 2)
-                 ORDER BY expense_date DESC",
+                 WHERE user_id = ? AND deleted_at IS NULL AND category = ? AND expense_date BETWEEN ? AND ?
+                 ORDER BY expense_date, id",
             )
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
         let mut rows = stmt
             .query(params![
-                user_id, category, start_date, start_date, start_date, end_date, end_date, end_date
+                user_id,
+                category,
+                start_date.to_string(),
+                end_date.to_string()
             ])
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
@@ -322,7 +864,7 @@ impl DatabaseService {
 
         let conn = self.get_connection().await?;
         let stmt = conn
-            .prepare("SELECT DISTINCT category FROM expenses WHERE user_id = ? ORDER BY category")
+            .prepare("SELECT DISTINCT category FROM expenses WHERE user_id = ? AND deleted_at IS NULL ORDER BY category")
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
@@ -347,18 +889,99 @@ impl DatabaseService {
         Ok(categories)
     }
 
-    // Find expense by message ID (for reply-based modifications)
-    pub async fn find_expense_by_message(
+    // Get the user's most used categories, most frequent first
+    pub async fn get_top_categories(
+        &self,
+        user_id: i64,
+        limit: i64,
+    ) -> Result<Vec<String>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT category FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL
+                 GROUP BY category
+                 ORDER BY COUNT(*) DESC, MAX(created_at) DESC
+                 LIMIT ?",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, limit])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut categories = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            let category: String = row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            categories.push(category);
+        }
+        Ok(categories)
+    }
+
+    // Get the most recently used category for each expense item
+    pub async fn get_item_categories(
+        &self,
+        user_id: i64,
+    ) -> Result<HashMap<String, String>, DatabaseError> {
+        if let Some(cache) = self.item_category_cache.get(&user_id) {
+            return Ok(cache);
+        }
+
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT description, category FROM expenses WHERE user_id = ? AND deleted_at IS NULL ORDER BY id DESC LIMIT ?",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, ITEM_CATEGORY_LOOKBACK])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut item_categories = HashMap::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            let description: String = row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            let category: String = row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            item_categories
+                .entry(description_item(&description))
+                .or_insert(category);
+        }
+        self.item_category_cache
+            .insert(user_id, item_categories.clone());
+        Ok(item_categories)
+    }
+
+    // Find expenses by message ID (for reply-based modifications), one message can add several
+    pub async fn find_expenses_by_message(
         &self,
         user_id: i64,
         message_id: i64,
-    ) -> Result<Option<Expense>, DatabaseError> {
+    ) -> Result<Vec<Expense>, DatabaseError> {
         let conn = self.get_connection().await?;
         let stmt = conn
             .prepare(
-                "SELECT id, user_id, amount, description, category, expense_date, user_message_id, bot_message_id, created_at
+                "SELECT id, user_id, amount_minor, description, category, expense_date, user_message_id, bot_message_id, created_at, currency, account, member_id
                  FROM expenses
-                 WHERE user_id = ? AND (user_message_id = ? OR bot_message_id = ?)",
+                 WHERE user_id = ? AND deleted_at IS NULL AND (user_message_id = ? OR bot_message_id = ?)
+                 ORDER BY id",
             )
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
@@ -368,6 +991,112 @@ impl DatabaseService {
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
+        let mut expenses = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            expenses.push(Expense::from_row(&row)?);
+        }
+        Ok(expenses)
+    }
+
+    // Find cash transaction by message ID
+    pub async fn find_cash_by_message(
+        &self,
+        user_id: i64,
+        message_id: i64,
+    ) -> Result<Vec<CashTransaction>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, amount_minor, transaction_date, user_message_id, bot_message_id, created_at, currency, account
+                 FROM cash_transactions
+                 WHERE user_id = ? AND (user_message_id = ? OR bot_message_id = ?)
+                 ORDER BY id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, message_id, message_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut transactions = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            transactions.push(CashTransaction::from_row(&row)?);
+        }
+        Ok(transactions)
+    }
+
+    // Records created by or announced in a message, for replies to it. A message that
+    // added several entries returns all of them.
+    pub async fn find_records_by_message(
+        &self,
+        user_id: i64,
+        message_id: i64,
+    ) -> Result<Vec<RecordContext>, DatabaseError> {
+        let mut records = Vec::new();
+        records.extend(
+            self.find_expenses_by_message(user_id, message_id)
+                .await?
+                .into_iter()
+                .map(RecordContext::Expense),
+        );
+        records.extend(
+            self.find_cash_by_message(user_id, message_id)
+                .await?
+                .into_iter()
+                .map(RecordContext::CashTransaction),
+        );
+        records.extend(
+            self.find_income_by_message(user_id, message_id)
+                .await?
+                .into_iter()
+                .map(RecordContext::Income),
+        );
+        records.extend(
+            self.find_settlements_by_message(user_id, message_id)
+                .await?
+                .into_iter()
+                .map(RecordContext::Settlement),
+        );
+        records.extend(
+            self.find_transfers_by_message(user_id, message_id)
+                .await?
+                .into_iter()
+                .map(RecordContext::Transfer),
+        );
+        Ok(records)
+    }
+
+    // Get expense by ID
+    pub async fn get_expense(
+        &self,
+        user_id: i64,
+        expense_id: i64,
+    ) -> Result<Option<Expense>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, amount_minor, description, category, expense_date, user_message_id, bot_message_id, created_at, currency, account, member_id
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL AND id = ?",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, expense_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
         if let Some(row) = rows
             .next()
             .await
@@ -379,24 +1108,24 @@ impl DatabaseService {
         }
     }
 
-    // Find cash transaction by message ID
-    pub async fn find_cash_by_message(
+    // Get cash transaction by ID
+    pub async fn get_cash_transaction(
         &self,
         user_id: i64,
-        message_id: i64,
+        transaction_id: i64,
     ) -> Result<Option<CashTransaction>, DatabaseError> {
         let conn = self.get_connection().await?;
         let stmt = conn
             .prepare(
-                "SELECT id, user_id, amount, transaction_date, user_message_id, bot_message_id, created_at
+                "SELECT id, user_id, amount_minor, transaction_date, user_message_id, bot_message_id, created_at, currency, account
                  FROM cash_transactions
-                 WHERE user_id = ? AND (user_message_id = ? OR bot_message_id = ?)",
+                 WHERE user_id = ? AND id = ?",
             )
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
         let mut rows = stmt
-            .query(params![user_id, message_id, message_id])
+            .query(params![user_id, transaction_id])
             .await
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
 
@@ -410,4 +1139,288 @@ impl DatabaseService {
             Ok(None)
         }
     }
+
+    // Set or replace the monthly budget for a category
+    pub async fn set_budget(
+        &self,
+        user_id: i64,
+        category: &str,
+        amount: Money,
+    ) -> Result<(), DatabaseError> {
+        self.execute(
+            "INSERT INTO budgets (user_id, category, amount_minor, currency, created_at, updated_at)
+             VALUES (?, ?, ?, ?, datetime('now'), datetime('now'))
+             ON CONFLICT (user_id, category) DO UPDATE SET
+                amount_minor = excluded.amount_minor,
+                currency = excluded.currency,
+                updated_at = datetime('now')",
+            params![user_id, category, amount.minor(), amount.currency().code()],
+        )
+        .await
+    }
+
+    // Delete budget, returns false if there was no budget for the category
+    pub async fn delete_budget(&self, user_id: i64, category: &str) -> Result<bool, DatabaseError> {
+        let deleted = self
+            .execute_returning_count(
+                "DELETE FROM budgets WHERE user_id = ? AND category = ?",
+                params![user_id, category],
+            )
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    // Get all budgets for user
+    pub async fn get_budgets(&self, user_id: i64) -> Result<Vec<Budget>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, category, amount_minor, currency, created_at
+                 FROM budgets
+                 WHERE user_id = ?
+                 ORDER BY category",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut budgets = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            budgets.push(Budget::from_row(&row)?);
+        }
+        Ok(budgets)
+    }
+
+    // Get budget for a single category
+    pub async fn get_budget(
+        &self,
+        user_id: i64,
+        category: &str,
+    ) -> Result<Option<Budget>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, category, amount_minor, currency, created_at
+                 FROM budgets
+                 WHERE user_id = ? AND category = ?",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, category])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        if let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            Ok(Some(Budget::from_row(&row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Get total spent in a category for date range
+    pub async fn get_category_total(
+        &self,
+        user_id: i64,
+        category: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Money, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT COALESCE(SUM(amount_minor), 0)
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL AND category = ? COLLATE NOCASE AND expense_date BETWEEN ? AND ?",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                category,
+                start_date.to_string(),
+                end_date.to_string()
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        if let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            let total: i64 = row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            Ok(Money::from_minor(total, Currency::default()))
+        } else {
+            Ok(Money::zero())
+        }
+    }
+
+    // Add recurring rule, returns rule_id
+    pub async fn add_recurring_rule(
+        &self,
+        args: &AddRecurringArgs,
+        session_context: &SessionContext,
+    ) -> Result<i64, DatabaseError> {
+        self.execute_returning_id(
+            "INSERT INTO recurring_rules (user_id, kind, amount_minor, currency, description, category, frequency, anchor_day, next_run_date, user_message_id, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+            params![
+                session_context.user_id,
+                args.kind.as_str(),
+                args.amount.minor(),
+                args.amount.currency().code(),
+                args.description.clone(),
+                args.category.clone(),
+                args.frequency.as_str(),
+                args.start_date.day(),
+                args.start_date.to_string(),
+                session_context.user_message_id
+            ],
+        )
+        .await
+    }
+
+    // Get active and paused recurring rules for user
+    pub async fn get_recurring_rules(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<RecurringRule>, DatabaseError> {
+        self.query_recurring_rules(
+            "SELECT id, user_id, kind, amount_minor, currency, description, category, frequency, anchor_day, next_run_date, status, created_at
+             FROM recurring_rules
+             WHERE user_id = ? AND status != 'cancelled'
+             ORDER BY next_run_date, id",
+            params![user_id],
+        )
+        .await
+    }
+
+    // Get active rules for all users whose next run is on or before the date
+    pub async fn get_due_recurring_rules(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<RecurringRule>, DatabaseError> {
+        self.query_recurring_rules(
+            "SELECT id, user_id, kind, amount_minor, currency, description, category, frequency, anchor_day, next_run_date, status, created_at
+             FROM recurring_rules
+             WHERE status = 'active' AND next_run_date <= ?
+             ORDER BY next_run_date, id",
+            params![date.to_string()],
+        )
+        .await
+    }
+
+    async fn query_recurring_rules(
+        &self,
+        sql: &str,
+        params: impl IntoParams,
+    ) -> Result<Vec<RecurringRule>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(sql)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rules = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            rules.push(RecurringRule::from_row(&row)?);
+        }
+        Ok(rules)
+    }
+
+    // Change rule status, returns false if the user has no such rule or it is cancelled
+    pub async fn set_recurring_status(
+        &self,
+        user_id: i64,
+        rule_id: i64,
+        status: RecurringStatus,
+    ) -> Result<bool, DatabaseError> {
+        let updated = self
+            .execute_returning_count(
+                "UPDATE recurring_rules SET status = ?
+                 WHERE id = ? AND user_id = ? AND status != 'cancelled'",
+                params![status.as_str(), rule_id, user_id],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    // Resume a paused rule from its first occurrence on or after today, so the periods it
+    // was paused for are not caught up on. Returns false if the user has no such rule or
+    // it is cancelled.
+    pub async fn resume_recurring_rule(
+        &self,
+        user_id: i64,
+        rule_id: i64,
+        today: NaiveDate,
+    ) -> Result<bool, DatabaseError> {
+        let Some(rule) = self
+            .query_recurring_rules(
+                "SELECT id, user_id, kind, amount_minor, currency, description, category, frequency, anchor_day, next_run_date, status, created_at
+                 FROM recurring_rules
+                 WHERE id = ? AND user_id = ? AND status != 'cancelled'",
+                params![rule_id, user_id],
+            )
+            .await?
+            .pop()
+        else {
+            return Ok(false);
+        };
+        if rule.status == RecurringStatus::Paused {
+            let next_run_date =
+                rule.frequency
+                    .first_on_or_after(rule.next_run_date, rule.anchor_day, today);
+            self.execute(
+                "UPDATE recurring_rules SET status = 'active', next_run_date = ?
+                 WHERE id = ? AND user_id = ? AND status = 'paused'",
+                params![next_run_date.to_string(), rule_id, user_id],
+            )
+            .await?;
+        }
+        Ok(true)
+    }
+
+    // Move the next run date forward, returns false if another run already advanced it
+    pub async fn advance_recurring_rule(
+        &self,
+        rule_id: i64,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<bool, DatabaseError> {
+        let updated = self
+            .execute_returning_count(
+                "UPDATE recurring_rules SET next_run_date = ?
+                 WHERE id = ? AND next_run_date = ? AND status = 'active'",
+                params![to.to_string(), rule_id, from.to_string()],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
 }
@@ -0,0 +1,62 @@
+use super::{DatabaseError, DatabaseService, Expense};
+use chrono::NaiveDate;
+use libsql::params;
+
+// Match every word of the search as a prefix, e.g. "uber rides" -> "uber"* "rides"*.
+// Words are quoted so FTS5 operators typed by the user are taken literally.
+fn match_expression(search: &str) -> Option<String> {
+    let terms: Vec<String> = search
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word.to_lowercase()))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+impl DatabaseService {
+    // Expenses whose description matches every word of the search, oldest first.
+    // Without dates the whole history is searched.
+    pub async fn search_expenses(
+        &self,
+        user_id: i64,
+        search: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> Result<Vec<Expense>, DatabaseError> {
+        let Some(expression) = match_expression(search) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT id, user_id, amount_minor, description, category, expense_date, user_message_id, bot_message_id, created_at, currency, account, member_id
+                 FROM expenses
+                 WHERE user_id = ? AND deleted_at IS NULL
+                   AND id IN (SELECT rowid FROM expenses_fts WHERE expenses_fts MATCH ?)
+                   AND expense_date BETWEEN COALESCE(?, '0000-01-01') AND COALESCE(?, '9999-12-31')
+                 ORDER BY expense_date, id",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![
+                user_id,
+                expression,
+                start_date.map(|d| d.to_string()),
+                end_date.map(|d| d.to_string())
+            ])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut expenses = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            expenses.push(Expense::from_row(&row)?);
+        }
+        Ok(expenses)
+    }
+}
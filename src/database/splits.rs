@@ -0,0 +1,419 @@
+use super::{
+    DatabaseError, DatabaseService, JournalAction, PersonBalance, Settlement, SplitEntry,
+    insert_expense, journal,
+};
+use crate::core::Money;
+use crate::request::types::{
+    ActionType, SessionContext,
+    args::{AddExpenseArgs, ModifySettlementArgs},
+};
+use chrono::NaiveDate;
+use libsql::params;
+
+const SETTLEMENT_SELECT: &str = "SELECT id, user_id, person, amount_minor, currency, settlement_date, account, user_message_id, bot_message_id, created_at
+     FROM settlements";
+
+// Owed amount of a share, rounded down so the remainder stays with the user
+const SHARE_AMOUNT: &str = "e.amount_minor * s.weight / s.total_weight";
+
+impl DatabaseService {
+    // Stored spelling of a person already split with, names match case-insensitively
+    pub async fn person_name(&self, user_id: i64, name: &str) -> Result<String, DatabaseError> {
+        let name = name.trim();
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(
+                "SELECT person FROM expense_shares WHERE user_id = ? AND person = ? COLLATE NOCASE
+                 UNION ALL
+                 SELECT person FROM settlements WHERE user_id = ? AND person = ? COLLATE NOCASE
+                 LIMIT 1",
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params![user_id, name, user_id, name])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        match rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            Some(row) => row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string())),
+            None => Ok(name.to_string()),
+        }
+    }
+
+    // Adds the expense and its shares together, so a failed share never leaves a
+    // half-split expense behind. Shares are stored as weights of total_weight, see the
+    // splits migration.
+    pub async fn add_split_expense(
+        &self,
+        args: &AddExpenseArgs,
+        shares: &[(String, i64)],
+        total_weight: i64,
+        ctx: &SessionContext,
+        tool_name: &str,
+    ) -> Result<i64, DatabaseError> {
+        let account = self.prepare_expense(args, ctx).await?;
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let expense_id = insert_expense(&tx, args, &account, ctx, tool_name).await?;
+        for (person, weight) in shares {
+            tx.execute(
+                "INSERT INTO expense_shares (expense_id, user_id, person, weight, total_weight, created_at)
+                 VALUES (?, ?, ?, ?, ?, datetime('now'))",
+                params![expense_id, ctx.user_id, person.as_str(), *weight, total_weight],
+            )
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(expense_id)
+    }
+
+    // What each person owes on an expense, empty if it was not split
+    pub async fn get_expense_shares(
+        &self,
+        user_id: i64,
+        expense_id: i64,
+    ) -> Result<Vec<PersonBalance>, DatabaseError> {
+        self.query_person_balances(
+            &format!(
+                "SELECT s.person, {}
+                 FROM expense_shares s
+                 JOIN expenses e ON e.id = s.expense_id
+                 WHERE s.user_id = ? AND s.expense_id = ?
+                 ORDER BY s.id",
+                SHARE_AMOUNT
+            ),
+            params![user_id, expense_id],
+        )
+        .await
+    }
+
+    // Outstanding balance with everyone, largest debt to the user first. Shares of
+    // deleted expenses no longer count.
+    pub async fn get_split_balances(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<PersonBalance>, DatabaseError> {
+        self.query_person_balances(
+            &format!(
+                "SELECT person, SUM(amount) AS balance FROM (
+                    SELECT s.person, {} AS amount
+                    FROM expense_shares s
+                    JOIN expenses e ON e.id = s.expense_id
+                    WHERE s.user_id = ? AND e.deleted_at IS NULL
+                    UNION ALL
+                    SELECT person, -amount_minor FROM settlements WHERE user_id = ?
+                 )
+                 GROUP BY person COLLATE NOCASE
+                 HAVING balance != 0
+                 ORDER BY balance DESC",
+                SHARE_AMOUNT
+            ),
+            params![user_id, user_id],
+        )
+        .await
+    }
+
+    // What the person owes the user, negative when the user owes them
+    pub async fn get_person_balance(
+        &self,
+        user_id: i64,
+        person: &str,
+    ) -> Result<Money, DatabaseError> {
+        Ok(self
+            .get_split_balances(user_id)
+            .await?
+            .into_iter()
+            .find(|b| b.person.eq_ignore_ascii_case(person.trim()))
+            .map(|b| b.balance)
+            .unwrap_or(Money::zero()))
+    }
+
+    async fn query_person_balances(
+        &self,
+        sql: &str,
+        params: impl libsql::params::IntoParams,
+    ) -> Result<Vec<PersonBalance>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(sql)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut balances = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            balances.push(PersonBalance::from_row(&row)?);
+        }
+        Ok(balances)
+    }
+
+    // Shares and settlements with one person, oldest first
+    pub async fn get_split_entries(
+        &self,
+        user_id: i64,
+        person: &str,
+    ) -> Result<Vec<SplitEntry>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(&format!(
+                "SELECT date, description, amount FROM (
+                    SELECT e.expense_date AS date, e.description AS description, {} AS amount, e.created_at AS created_at
+                    FROM expense_shares s
+                    JOIN expenses e ON e.id = s.expense_id
+                    WHERE s.user_id = ? AND s.person = ? COLLATE NOCASE AND e.deleted_at IS NULL
+                    UNION ALL
+                    SELECT settlement_date, 'Settled', -amount_minor, created_at
+                    FROM settlements
+                    WHERE user_id = ? AND person = ? COLLATE NOCASE
+                 )
+                 ORDER BY date, created_at",
+                SHARE_AMOUNT
+            ))
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let person = person.trim();
+        let mut rows = stmt
+            .query(params![user_id, person, user_id, person])
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            entries.push(SplitEntry::from_row(&row)?);
+        }
+        Ok(entries)
+    }
+
+    // Amount is positive when the person paid the user
+    pub async fn add_settlement(
+        &self,
+        person: &str,
+        amount: Money,
+        date: NaiveDate,
+        account: Option<&str>,
+        ctx: &SessionContext,
+    ) -> Result<i64, DatabaseError> {
+        let person = self.person_name(ctx.user_id, person).await?;
+        let account = self.account_name(ctx.user_id, account).await?;
+        let settlement_id = self
+            .execute_returning_id(
+                "INSERT INTO settlements (user_id, person, amount_minor, currency, settlement_date, account, user_message_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+                params![
+                    ctx.user_id,
+                    person,
+                    amount.minor(),
+                    amount.currency().code(),
+                    date.to_string(),
+                    account,
+                    ctx.user_message_id
+                ],
+            )
+            .await?;
+        self.record_action(
+            ctx,
+            JournalAction::Create,
+            &ActionType::Settlement,
+            settlement_id,
+            None,
+        )
+        .await?;
+        Ok(settlement_id)
+    }
+
+    // A new amount keeps the direction of the settlement
+    pub async fn modify_settlement(
+        &self,
+        args: ModifySettlementArgs,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let mut set_clauses = Vec::new();
+        let mut values: Vec<libsql::Value> = Vec::new();
+
+        if let Some(amt) = args.amount {
+            set_clauses.push("amount_minor = CASE WHEN amount_minor < 0 THEN -? ELSE ? END");
+            values.push(amt.minor().abs().into());
+            values.push(amt.minor().abs().into());
+        }
+        if let Some(d) = args.date {
+            set_clauses.push("settlement_date = ?");
+            values.push(d.to_string().into());
+        }
+        if let Some(account) = &args.account {
+            set_clauses.push("account = ?");
+            values.push(self.account_name(ctx.user_id, Some(account)).await?.into());
+        }
+
+        if set_clauses.is_empty() {
+            return Err(DatabaseError::QueryError("No fields to update".to_string()));
+        }
+
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let Some(previous_state) = journal::snapshot_record(
+            &tx,
+            &ActionType::Settlement,
+            ctx.user_id,
+            args.settlement_id,
+        )
+        .await?
+        else {
+            return Err(DatabaseError::SettlementNotFound(args.settlement_id));
+        };
+        let sql = format!(
+            "UPDATE settlements SET {} WHERE id = ? AND user_id = ?",
+            set_clauses.join(", ")
+        );
+        values.push(args.settlement_id.into());
+        values.push(ctx.user_id.into());
+        tx.execute(&sql, libsql::params::Params::Positional(values))
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        journal::insert_journal_entry(
+            &tx,
+            ctx,
+            JournalAction::Modify,
+            &ActionType::Settlement,
+            args.settlement_id,
+            Some(previous_state),
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    pub async fn delete_settlement(
+        &self,
+        settlement_id: i64,
+        ctx: &SessionContext,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.get_connection().await?;
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let Some(previous_state) =
+            journal::snapshot_record(&tx, &ActionType::Settlement, ctx.user_id, settlement_id)
+                .await?
+        else {
+            return Err(DatabaseError::SettlementNotFound(settlement_id));
+        };
+        tx.execute(
+            "DELETE FROM settlements WHERE id = ? AND user_id = ?",
+            params![settlement_id, ctx.user_id],
+        )
+        .await
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        journal::insert_journal_entry(
+            &tx,
+            ctx,
+            JournalAction::Delete,
+            &ActionType::Settlement,
+            settlement_id,
+            Some(previous_state),
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    pub async fn update_settlement_bot_message(
+        &self,
+        settlement_id: i64,
+        bot_message_id: i64,
+    ) -> Result<(), DatabaseError> {
+        self.execute(
+            "UPDATE settlements SET bot_message_id = ? WHERE id = ?",
+            params![bot_message_id, settlement_id],
+        )
+        .await
+    }
+
+    pub async fn get_settlement(
+        &self,
+        user_id: i64,
+        settlement_id: i64,
+    ) -> Result<Option<Settlement>, DatabaseError> {
+        Ok(self
+            .query_settlements(
+                &format!("{} WHERE user_id = ? AND id = ?", SETTLEMENT_SELECT),
+                params![user_id, settlement_id],
+            )
+            .await?
+            .pop())
+    }
+
+    pub async fn find_settlements_by_message(
+        &self,
+        user_id: i64,
+        message_id: i64,
+    ) -> Result<Vec<Settlement>, DatabaseError> {
+        self.query_settlements(
+            &format!(
+                "{} WHERE user_id = ? AND (user_message_id = ? OR bot_message_id = ?) ORDER BY id",
+                SETTLEMENT_SELECT
+            ),
+            params![user_id, message_id, message_id],
+        )
+        .await
+    }
+
+    async fn query_settlements(
+        &self,
+        sql: &str,
+        params: impl libsql::params::IntoParams,
+    ) -> Result<Vec<Settlement>, DatabaseError> {
+        let conn = self.get_connection().await?;
+        let stmt = conn
+            .prepare(sql)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut rows = stmt
+            .query(params)
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let mut settlements = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            settlements.push(Settlement::from_row(&row)?);
+        }
+        Ok(settlements)
+    }
+}
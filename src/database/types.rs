@@ -1,25 +1,148 @@
 use super::DatabaseError;
+use crate::core::{Currency, Money};
+use crate::request::types::RecordContext;
+use crate::request::types::args::{AddExpenseArgs, AddIncomeArgs};
+use chrono::{Datelike, Duration, Months, NaiveDate};
 use libsql::Row;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+// Amounts are stored as integer minor units alongside a currency code
+fn get_money(row: &Row, amount_idx: i32, currency_idx: i32) -> Result<Money, DatabaseError> {
+    let minor: i64 = row
+        .get(amount_idx)
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    let code: String = row
+        .get(currency_idx)
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    let currency = Currency::from_code(&code)
+        .ok_or_else(|| DatabaseError::QueryError(format!("Unknown currency '{}'", code)))?;
+    Ok(Money::from_minor(minor, currency))
+}
+
+// Dates are stored as ISO-8601 yyyy-mm-dd strings
+fn get_date(row: &Row, idx: i32) -> Result<NaiveDate, DatabaseError> {
+    let date: String = row
+        .get(idx)
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+    date.parse::<NaiveDate>()
+        .map_err(|e| DatabaseError::QueryError(format!("Invalid date '{}': {}", date, e)))
+}
 
 #[derive(Debug, Clone)]
 pub struct Expense {
     pub id: i64,
     pub user_id: i64,
-    pub amount: i64,
+    pub amount: Money,
     pub description: String,
     pub category: String,
-    pub expense_date: String,
+    pub expense_date: NaiveDate,
     pub user_message_id: i64,
     pub bot_message_id: Option<i64>,
     pub created_at: String,
+    pub account: String,
+    // Group member who added the expense, None when anyone may change it
+    pub member_id: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
 pub struct CashTransaction {
     pub id: i64,
     pub user_id: i64,
-    pub amount: i64,
-    pub transaction_date: String,
+    pub amount: Money,
+    pub transaction_date: NaiveDate,
+    pub user_message_id: i64,
+    pub bot_message_id: Option<i64>,
+    pub created_at: String,
+    pub account: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Income {
+    pub id: i64,
+    pub user_id: i64,
+    pub amount: Money,
+    // Who paid, e.g. "Acme Corp" or "Amazon refund"
+    pub source: String,
+    pub category: String,
+    pub income_date: NaiveDate,
+    pub account: String,
+    pub user_message_id: i64,
+    pub bot_message_id: Option<i64>,
+    pub created_at: String,
+}
+
+// Income and expenses of one month
+#[derive(Debug, Clone)]
+pub struct MonthlyIncomeExpense {
+    // First day of the month
+    pub month: NaiveDate,
+    pub income: Money,
+    pub expenses: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub id: i64,
+    pub user_id: i64,
+    pub person: String,
+    // Positive when the person paid the user, negative when the user paid them
+    pub amount: Money,
+    pub settlement_date: NaiveDate,
+    pub account: String,
+    pub user_message_id: i64,
+    pub bot_message_id: Option<i64>,
+    pub created_at: String,
+}
+
+// What a person owes the user, negative when the user owes them
+#[derive(Debug, Clone)]
+pub struct PersonBalance {
+    pub person: String,
+    pub balance: Money,
+}
+
+// A share or settlement with one person, amount as it changes what they owe
+#[derive(Debug, Clone)]
+pub struct SplitEntry {
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountKind {
+    Cash,
+    Bank,
+    Card,
+    Wallet,
+}
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub kind: AccountKind,
+    pub is_default: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountBalance {
+    pub account: Account,
+    pub balance: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub id: i64,
+    pub user_id: i64,
+    pub from_account: String,
+    pub to_account: String,
+    pub amount: Money,
+    pub transfer_date: NaiveDate,
     pub user_message_id: i64,
     pub bot_message_id: Option<i64>,
     pub created_at: String,
@@ -28,7 +151,142 @@ pub struct CashTransaction {
 #[derive(Debug, Clone)]
 pub struct CategorySummary {
     pub category: String,
-    pub total: i64,
+    pub total: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pub id: i64,
+    pub user_id: i64,
+    pub category: String,
+    // Monthly limit
+    pub amount: Money,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecurringKind {
+    Expense,
+    Cash,
+    Income,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecurringFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, Clone)]
+pub struct DigestSubscription {
+    pub user_id: i64,
+    pub frequency: DigestFrequency,
+    pub next_run_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecurringStatus {
+    Active,
+    Paused,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecurringRule {
+    pub id: i64,
+    pub user_id: i64,
+    pub kind: RecurringKind,
+    pub amount: Money,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub frequency: RecurringFrequency,
+    // Day of month the rule was set up for, so 31st rules return to 31 after short months
+    pub anchor_day: u32,
+    pub next_run_date: NaiveDate,
+    pub status: RecurringStatus,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JournalAction {
+    Create,
+    Modify,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryChange {
+    Create,
+    Modify,
+    Delete,
+    Restore,
+}
+
+// One entry of an expense's change history, values hold the tracked columns
+#[derive(Debug, Clone)]
+pub struct ExpenseHistoryEntry {
+    pub id: i64,
+    pub expense_id: i64,
+    pub change: HistoryChange,
+    pub old_values: Option<Map<String, Value>>,
+    pub new_values: Option<Map<String, Value>>,
+    pub source_message_id: i64,
+    pub tool_name: String,
+    pub created_at: String,
+}
+
+// A journalled action that was reversed, with the record as it was affected
+#[derive(Debug, Clone)]
+pub struct UndoneAction {
+    pub action: JournalAction,
+    pub record: RecordContext,
+}
+
+// One imported row, statement rows carry the hash that marks them as imported
+#[derive(Debug, Clone)]
+pub struct ImportEntry<T> {
+    pub args: T,
+    pub import_hash: Option<String>,
+}
+
+// Expenses and cash transactions inserted together by an import
+#[derive(Debug, Clone, Default)]
+pub struct ImportBatch {
+    pub expenses: Vec<ImportEntry<AddExpenseArgs>>,
+    pub income: Vec<ImportEntry<AddIncomeArgs>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportCounts {
+    pub expenses: usize,
+    pub income: usize,
+    // Statement rows skipped because they were imported in the meantime
+    pub duplicates: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct DailyTotal {
+    pub date: NaiveDate,
+    pub total: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct MonthlyCategorySummary {
+    // First day of the month
+    pub month: NaiveDate,
+    pub category: String,
+    pub total: Money,
 }
 
 impl Expense {
@@ -40,18 +298,14 @@ impl Expense {
             user_id: row
                 .get(1)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
-            amount: row
-                .get(2)
-                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            amount: get_money(row, 2, 9)?,
             description: row
                 .get(3)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
             category: row
                 .get(4)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
-            expense_date: row
-                .get(5)
-                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            expense_date: get_date(row, 5)?,
             user_message_id: row
                 .get(6)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
@@ -61,6 +315,12 @@ impl Expense {
             created_at: row
                 .get(8)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            account: row
+                .get(10)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            member_id: row
+                .get(11)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
         })
     }
 }
@@ -74,34 +334,576 @@ impl CashTransaction {
             user_id: row
                 .get(1)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
-            amount: row
+            amount: get_money(row, 2, 7)?,
+            transaction_date: get_date(row, 3)?,
+            user_message_id: row
+                .get(4)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            bot_message_id: row
+                .get(5)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            created_at: row
+                .get(6)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            account: row
+                .get(8)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl AccountKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountKind::Cash => "cash",
+            AccountKind::Bank => "bank",
+            AccountKind::Card => "card",
+            AccountKind::Wallet => "wallet",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "cash" => Some(AccountKind::Cash),
+            "bank" => Some(AccountKind::Bank),
+            "card" => Some(AccountKind::Card),
+            "wallet" => Some(AccountKind::Wallet),
+            _ => None,
+        }
+    }
+}
+
+impl Account {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let kind: String = row
+            .get(3)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let is_default: i64 = row
+            .get(4)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            name: row
+                .get(2)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            kind: AccountKind::parse(&kind).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown account kind '{}'", kind))
+            })?,
+            is_default: is_default != 0,
+            created_at: row
+                .get(5)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl Transfer {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            from_account: row
                 .get(2)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
-            transaction_date: row
+            to_account: row
                 .get(3)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            amount: get_money(row, 4, 5)?,
+            transfer_date: get_date(row, 6)?,
             user_message_id: row
-                .get(4)
+                .get(7)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
             bot_message_id: row
+                .get(8)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            created_at: row
+                .get(9)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl CategorySummary {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            category: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            total: Money::from_minor(
+                row.get(1)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                Currency::default(),
+            ),
+        })
+    }
+}
+
+impl Budget {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            category: row
+                .get(2)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            amount: get_money(row, 3, 4)?,
+            created_at: row
                 .get(5)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl RecurringKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringKind::Expense => "expense",
+            RecurringKind::Cash => "cash",
+            RecurringKind::Income => "income",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "expense" => Some(RecurringKind::Expense),
+            "cash" => Some(RecurringKind::Cash),
+            "income" => Some(RecurringKind::Income),
+            _ => None,
+        }
+    }
+}
+
+impl RecurringFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringFrequency::Daily => "daily",
+            RecurringFrequency::Weekly => "weekly",
+            RecurringFrequency::Monthly => "monthly",
+            RecurringFrequency::Yearly => "yearly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "daily" => Some(RecurringFrequency::Daily),
+            "weekly" => Some(RecurringFrequency::Weekly),
+            "monthly" => Some(RecurringFrequency::Monthly),
+            "yearly" => Some(RecurringFrequency::Yearly),
+            _ => None,
+        }
+    }
+
+    // Next occurrence after date, monthly and yearly rules stay on the anchor day when it exists
+    pub fn next_date(&self, date: NaiveDate, anchor_day: u32) -> NaiveDate {
+        let next = match self {
+            RecurringFrequency::Daily => date + Duration::days(1),
+            RecurringFrequency::Weekly => date + Duration::weeks(1),
+            RecurringFrequency::Monthly => date
+                .checked_add_months(Months::new(1))
+                .unwrap_or(date + Duration::days(30)),
+            RecurringFrequency::Yearly => date
+                .checked_add_months(Months::new(12))
+                .unwrap_or(date + Duration::days(365)),
+        };
+        match self {
+            // Largest day not past the anchor that exists in the target month
+            RecurringFrequency::Monthly | RecurringFrequency::Yearly => (1..=anchor_day.max(1))
+                .rev()
+                .find_map(|day| next.with_day(day))
+                .unwrap_or(next),
+            _ => next,
+        }
+    }
+
+    // First occurrence on or after date, stepping from an earlier occurrence
+    pub fn first_on_or_after(
+        &self,
+        mut occurrence: NaiveDate,
+        anchor_day: u32,
+        date: NaiveDate,
+    ) -> NaiveDate {
+        while occurrence < date {
+            occurrence = self.next_date(occurrence, anchor_day);
+        }
+        occurrence
+    }
+
+    // Latest occurrence on or before date, stepping from an occurrence not after it
+    pub fn latest_on_or_before(
+        &self,
+        mut occurrence: NaiveDate,
+        anchor_day: u32,
+        date: NaiveDate,
+    ) -> NaiveDate {
+        loop {
+            let next = self.next_date(occurrence, anchor_day);
+            if next > date {
+                return occurrence;
+            }
+            occurrence = next;
+        }
+    }
+}
+
+impl DigestFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestFrequency::Weekly => "weekly",
+            DigestFrequency::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "weekly" => Some(DigestFrequency::Weekly),
+            "monthly" => Some(DigestFrequency::Monthly),
+            _ => None,
+        }
+    }
+
+    // Weekly digests go out on Mondays and monthly ones on the 1st, strictly after date
+    pub fn next_run_after(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            DigestFrequency::Weekly => {
+                date + Duration::days(7 - date.weekday().num_days_from_monday() as i64)
+            }
+            DigestFrequency::Monthly => (date.with_day(1).unwrap_or(date))
+                .checked_add_months(Months::new(1))
+                .unwrap_or(date + Duration::days(30)),
+        }
+    }
+
+    // Latest Monday or 1st of the month on or before date
+    pub fn latest_run(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            DigestFrequency::Weekly => {
+                date - Duration::days(date.weekday().num_days_from_monday() as i64)
+            }
+            DigestFrequency::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    // The week or month that ended the day before a digest sent on run_date
+    pub fn period_before(&self, run_date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let end = run_date - Duration::days(1);
+        let start = match self {
+            DigestFrequency::Weekly => {
+                end - Duration::days(end.weekday().num_days_from_monday() as i64)
+            }
+            DigestFrequency::Monthly => end.with_day(1).unwrap_or(end),
+        };
+        (start, end)
+    }
+}
+
+impl DigestSubscription {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let frequency: String = row
+            .get(1)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(Self {
+            user_id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            frequency: DigestFrequency::parse(&frequency).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown digest frequency '{}'", frequency))
+            })?,
+            next_run_date: get_date(row, 2)?,
+        })
+    }
+}
+
+impl RecurringStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringStatus::Active => "active",
+            RecurringStatus::Paused => "paused",
+            RecurringStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "active" => Some(RecurringStatus::Active),
+            "paused" => Some(RecurringStatus::Paused),
+            "cancelled" => Some(RecurringStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+impl JournalAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JournalAction::Create => "create",
+            JournalAction::Modify => "modify",
+            JournalAction::Delete => "delete",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "create" => Some(JournalAction::Create),
+            "modify" => Some(JournalAction::Modify),
+            "delete" => Some(JournalAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+impl HistoryChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryChange::Create => "create",
+            HistoryChange::Modify => "modify",
+            HistoryChange::Delete => "delete",
+            HistoryChange::Restore => "restore",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "create" => Some(HistoryChange::Create),
+            "modify" => Some(HistoryChange::Modify),
+            "delete" => Some(HistoryChange::Delete),
+            "restore" => Some(HistoryChange::Restore),
+            _ => None,
+        }
+    }
+}
+
+impl ExpenseHistoryEntry {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let change: String = row
+            .get(2)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let values = |idx: i32| -> Result<Option<Map<String, Value>>, DatabaseError> {
+            let json: Option<String> = row
+                .get(idx)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            Ok(json
+                .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+                .and_then(|value| match value {
+                    Value::Object(map) => Some(map),
+                    _ => None,
+                }))
+        };
+        Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            expense_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            change: HistoryChange::parse(&change).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown history change '{}'", change))
+            })?,
+            old_values: values(3)?,
+            new_values: values(4)?,
+            source_message_id: row
+                .get(5)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            tool_name: row
+                .get(6)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
             created_at: row
+                .get(7)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl RecurringRule {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let text = |idx: i32| -> Result<String, DatabaseError> {
+            row.get(idx)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))
+        };
+        let kind = text(2)?;
+        let frequency = text(7)?;
+        let status = text(10)?;
+        let anchor_day: i64 = row
+            .get(8)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            kind: RecurringKind::parse(&kind).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown recurring kind '{}'", kind))
+            })?,
+            amount: get_money(row, 3, 4)?,
+            description: row
+                .get(5)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            category: row
                 .get(6)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            frequency: RecurringFrequency::parse(&frequency).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown recurring frequency '{}'", frequency))
+            })?,
+            anchor_day: anchor_day as u32,
+            next_run_date: get_date(row, 9)?,
+            status: RecurringStatus::parse(&status).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown recurring status '{}'", status))
+            })?,
+            created_at: text(11)?,
         })
     }
 }
 
-impl CategorySummary {
+impl DailyTotal {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            date: get_date(row, 0)?,
+            total: Money::from_minor(
+                row.get(1)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                Currency::default(),
+            ),
+        })
+    }
+}
+
+impl Income {
     pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
         Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            amount: get_money(row, 2, 3)?,
+            source: row
+                .get(4)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
             category: row
+                .get(5)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            income_date: get_date(row, 6)?,
+            account: row
+                .get(7)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_message_id: row
+                .get(8)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            bot_message_id: row
+                .get(9)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            created_at: row
+                .get(10)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl Settlement {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            id: row
+                .get(0)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_id: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            person: row
+                .get(2)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            amount: get_money(row, 3, 4)?,
+            settlement_date: get_date(row, 5)?,
+            account: row
+                .get(6)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            user_message_id: row
+                .get(7)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            bot_message_id: row
+                .get(8)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            created_at: row
+                .get(9)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        })
+    }
+}
+
+impl PersonBalance {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            person: row
                 .get(0)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
-            total: row
+            balance: Money::from_minor(
+                row.get(1)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                Currency::default(),
+            ),
+        })
+    }
+}
+
+impl SplitEntry {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            date: get_date(row, 0)?,
+            description: row
+                .get(1)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            amount: Money::from_minor(
+                row.get(2)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                Currency::default(),
+            ),
+        })
+    }
+}
+
+impl MonthlyIncomeExpense {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let total = |idx: i32| -> Result<Money, DatabaseError> {
+            Ok(Money::from_minor(
+                row.get(idx)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                Currency::default(),
+            ))
+        };
+        Ok(Self {
+            month: get_date(row, 0)?,
+            income: total(1)?,
+            expenses: total(2)?,
+        })
+    }
+}
+
+impl MonthlyCategorySummary {
+    pub fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            month: get_date(row, 0)?,
+            category: row
                 .get(1)
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+            total: Money::from_minor(
+                row.get(2)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                Currency::default(),
+            ),
         })
     }
 }
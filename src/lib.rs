@@ -3,6 +3,7 @@ pub mod configuration;
 pub mod core;
 pub mod database;
 pub mod request;
+pub mod scheduler;
 
 use thiserror::Error;
 
@@ -2,6 +2,7 @@ use cash_tracker::AppError;
 use cash_tracker::communication::{ErrorAlertService, TelegramService};
 use cash_tracker::configuration::Context;
 use cash_tracker::core::ServiceManager;
+use cash_tracker::scheduler::{DigestService, RecurringService};
 use dotenvy::dotenv;
 use std::env;
 use std::str::FromStr;
@@ -30,7 +31,9 @@ async fn main() -> Result<(), AppError> {
     let shared_error_receiver = Arc::new(Mutex::new(error_receiver));
     let mut service_manager = ServiceManager::new(context);
     service_manager.spawn_with_error_receiver::<ErrorAlertService>(shared_error_receiver);
-    service_manager.spawn::<TelegramService>(error_sender);
+    service_manager.spawn::<TelegramService>(error_sender.clone());
+    service_manager.spawn::<RecurringService>(error_sender.clone());
+    service_manager.spawn::<DigestService>(error_sender);
     service_manager
         .wait()
         .await
@@ -0,0 +1,107 @@
+use super::tools::percent_of;
+use super::visualization::generate_pie_chart;
+use crate::core::Money;
+use crate::database::{DatabaseError, DatabaseService, DigestFrequency};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+const DIGEST_TOP_EXPENSES: i64 = 5;
+
+// Spending summary for the period ending the day before run_date, with a pie chart
+// of the categories when anything was spent
+pub async fn build_digest(
+    database: &DatabaseService,
+    user_id: i64,
+    frequency: DigestFrequency,
+    run_date: NaiveDate,
+) -> Result<(String, Option<Vec<u8>>), DatabaseError> {
+    let (start, end) = frequency.period_before(run_date);
+    let (previous_start, previous_end) = frequency.period_before(start);
+    let (title, previous_name) = match frequency {
+        DigestFrequency::Weekly => ("Weekly", "the week before"),
+        DigestFrequency::Monthly => ("Monthly", "the month before"),
+    };
+    let mut text = format!(
+        "📊 {} digest, {} to {}\n",
+        title,
+        start.format("%d/%m/%Y"),
+        end.format("%d/%m/%Y")
+    );
+
+    let breakdown = database.get_expense_breakdown(user_id, start, end).await?;
+    if breakdown.is_empty() {
+        text.push_str("No expenses were recorded in this period");
+        return Ok((text, None));
+    }
+    let previous: HashMap<String, Money> = database
+        .get_expense_breakdown(user_id, previous_start, previous_end)
+        .await?
+        .into_iter()
+        .map(|summary| (summary.category, summary.total))
+        .collect();
+
+    let total: Money = breakdown.iter().map(|summary| summary.total).sum();
+    let previous_total: Money = previous.values().copied().sum();
+    text.push_str(&format!("Total: {}", total));
+    if previous_total.is_zero() {
+        text.push_str(&format!(", nothing was spent {}\n", previous_name));
+    } else {
+        text.push_str(&format!(
+            ", {} on {}\n",
+            change_text(total, previous_total),
+            previous_name
+        ));
+    }
+
+    text.push_str("\nBy category:\n");
+    for summary in &breakdown {
+        let change = match previous.get(&summary.category) {
+            Some(before) => change_text(summary.total, *before),
+            None => "new".to_string(),
+        };
+        text.push_str(&format!(
+            "{} - {} ({}%), {}\n",
+            summary.category,
+            summary.total,
+            percent_of(summary.total, total),
+            change
+        ));
+    }
+
+    text.push_str("\nTop expenses:\n");
+    for expense in database
+        .get_top_expenses(user_id, start, end, DIGEST_TOP_EXPENSES)
+        .await?
+    {
+        text.push_str(&format!(
+            "{} | {} | {} ({})\n",
+            expense.expense_date.format("%d/%m/%Y"),
+            expense.amount,
+            expense.description,
+            expense.category
+        ));
+    }
+
+    Ok((
+        text.trim_end().to_string(),
+        generate_pie_chart(&breakdown).ok(),
+    ))
+}
+
+// e.g. "up ₹500 (12%)" or "unchanged", the previous amount is never zero
+fn change_text(current: Money, previous: Money) -> String {
+    let difference = current - previous;
+    if difference.is_zero() {
+        return "unchanged".to_string();
+    }
+    format!(
+        "{} {} ({}%)",
+        if difference.is_negative() {
+            "down"
+        } else {
+            "up"
+        },
+        difference.abs(),
+        percent_of(difference.abs(), previous.abs())
+    )
+}
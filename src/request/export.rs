@@ -0,0 +1,204 @@
+use super::parser::parse_date;
+use super::types::ExportFile;
+use crate::core::csv;
+use crate::database::{CashTransaction, DatabaseError, DatabaseService, Expense, Income};
+use chrono::NaiveDate;
+use serde_json::json;
+
+// Used when the export has no date range
+const FIRST_DATE: NaiveDate = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+const LAST_DATE: NaiveDate = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+
+pub(super) const EXPORT_USAGE: &str = "Usage: /export [csv|json] [from <date>] [to <date>]\n\
+     e.g. /export, /export json, /export csv from 1.10.25 to 31.10.25";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl ExportRequest {
+    // Parses the words after /export, e.g. "json from 1.10.25 to 31.10.25" or "csv 1.10.25"
+    pub fn parse(args: &str) -> Option<Self> {
+        let mut request = Self {
+            format: ExportFormat::Csv,
+            start_date: None,
+            end_date: None,
+        };
+        let mut expect_end = false;
+        for word in args.split_whitespace().map(str::to_lowercase) {
+            match word.as_str() {
+                "csv" => request.format = ExportFormat::Csv,
+                "json" => request.format = ExportFormat::Json,
+                "from" => expect_end = false,
+                "to" | "till" | "until" => expect_end = true,
+                _ => {
+                    let date = parse_date(&word)?;
+                    if expect_end || request.start_date.is_some() {
+                        request.end_date = Some(date);
+                    } else {
+                        request.start_date = Some(date);
+                    }
+                }
+            }
+        }
+        if let (Some(start), Some(end)) = (request.start_date, request.end_date)
+            && start > end
+        {
+            return None;
+        }
+        Some(request)
+    }
+}
+
+pub async fn build_export(
+    database: &DatabaseService,
+    user_id: i64,
+    request: &ExportRequest,
+) -> Result<ExportFile, DatabaseError> {
+    let start = request.start_date.unwrap_or(FIRST_DATE);
+    let end = request.end_date.unwrap_or(LAST_DATE);
+    let expenses = database.get_expenses(user_id, start, end).await?;
+    let income = database.get_income_entries(user_id, start, end).await?;
+    let cash_transactions = database.get_cash_transactions(user_id, start, end).await?;
+
+    let (extension, content) = match request.format {
+        ExportFormat::Csv => ("csv", to_csv(&expenses, &income, &cash_transactions)),
+        ExportFormat::Json => ("json", to_json(&expenses, &income, &cash_transactions)),
+    };
+    let range = match (request.start_date, request.end_date) {
+        (None, None) => "all".to_string(),
+        (start, end) => format!(
+            "{}_{}",
+            start.map_or("start".to_string(), |d| d.to_string()),
+            end.map_or("end".to_string(), |d| d.to_string())
+        ),
+    };
+    Ok(ExportFile {
+        file_name: format!("cash-tracker-{}.{}", range, extension),
+        content: content.into_bytes(),
+        summary: format!(
+            "📤 Exported {} expenses, {} income entries and {} cash transactions",
+            expenses.len(),
+            income.len(),
+            cash_transactions.len()
+        ),
+    })
+}
+
+// One row per record, income rows put the source in the description and cash rows
+// leave description and category empty
+fn to_csv(
+    expenses: &[Expense],
+    income: &[Income],
+    cash_transactions: &[CashTransaction],
+) -> String {
+    let mut output = csv::format_row(&[
+        "type",
+        "id",
+        "date",
+        "amount",
+        "currency",
+        "description",
+        "category",
+        "account",
+    ]);
+    for expense in expenses {
+        output.push_str(&csv::format_row(&[
+            "expense",
+            &expense.id.to_string(),
+            &expense.expense_date.to_string(),
+            &expense.amount.amount_string(),
+            expense.amount.currency().code(),
+            &expense.description,
+            &expense.category,
+            &expense.account,
+        ]));
+    }
+    for entry in income {
+        output.push_str(&csv::format_row(&[
+            "income",
+            &entry.id.to_string(),
+            &entry.income_date.to_string(),
+            &entry.amount.amount_string(),
+            entry.amount.currency().code(),
+            &entry.source,
+            &entry.category,
+            &entry.account,
+        ]));
+    }
+    for cash in cash_transactions {
+        output.push_str(&csv::format_row(&[
+            "cash",
+            &cash.id.to_string(),
+            &cash.transaction_date.to_string(),
+            &cash.amount.amount_string(),
+            cash.amount.currency().code(),
+            "",
+            "",
+            &cash.account,
+        ]));
+    }
+    output
+}
+
+fn to_json(
+    expenses: &[Expense],
+    income: &[Income],
+    cash_transactions: &[CashTransaction],
+) -> String {
+    let expenses: Vec<_> = expenses
+        .iter()
+        .map(|expense| {
+            json!({
+                "id": expense.id,
+                "date": expense.expense_date.to_string(),
+                "amount": expense.amount,
+                "currency": expense.amount.currency().code(),
+                "description": expense.description,
+                "category": expense.category,
+                "account": expense.account,
+            })
+        })
+        .collect();
+    let income: Vec<_> = income
+        .iter()
+        .map(|entry| {
+            json!({
+                "id": entry.id,
+                "date": entry.income_date.to_string(),
+                "amount": entry.amount,
+                "currency": entry.amount.currency().code(),
+                "source": entry.source,
+                "category": entry.category,
+                "account": entry.account,
+            })
+        })
+        .collect();
+    let cash_transactions: Vec<_> = cash_transactions
+        .iter()
+        .map(|cash| {
+            json!({
+                "id": cash.id,
+                "date": cash.transaction_date.to_string(),
+                "amount": cash.amount,
+                "currency": cash.amount.currency().code(),
+                "account": cash.account,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({
+        "expenses": expenses,
+        "income": income,
+        "cash_transactions": cash_transactions,
+    }))
+    .unwrap_or_default()
+}
@@ -0,0 +1,532 @@
+use super::parser::parse_date;
+use super::statement::{
+    Direction, ParsedStatement, income_category, merchant_category, parse_statement,
+};
+use super::types::{
+    ImportPreview, SessionContext,
+    args::{AddExpenseArgs, AddIncomeArgs},
+};
+use super::{RequestError, RequestFulfilment};
+use crate::core::{Money, csv};
+use crate::database::{AccountKind, ImportBatch, ImportEntry};
+use calamine::{Data, DataType, Reader, open_workbook_auto_from_rs};
+use chrono::NaiveDate;
+use std::io::Cursor;
+use tracing::error;
+
+const MAX_IMPORT_ROWS: usize = 5000;
+const PREVIEW_INVALID_ROWS: usize = 5;
+const LLM_CATEGORIZE_BATCH: usize = 50;
+const UNCATEGORIZED: &str = "Uncategorized";
+const STATEMENT_ACCOUNT: &str = "Bank";
+const IMPORT_FORMAT_HINT: &str = "Expected columns: date, amount, description and optionally category, \
+     or a bank statement with date, narration and withdrawal/deposit columns";
+// Excel files are zip archives (xlsx) or OLE compound documents (xls)
+const XLSX_MAGIC: &[u8] = b"PK\x03\x04";
+const XLS_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0];
+
+const DATE_HEADERS: &[&str] = &["date", "expense date", "transaction date"];
+const AMOUNT_HEADERS: &[&str] = &["amount", "amount (rs)", "amount (inr)", "debit"];
+const DESCRIPTION_HEADERS: &[&str] = &[
+    "description",
+    "item",
+    "details",
+    "narration",
+    "note",
+    "remarks",
+];
+const CATEGORY_HEADERS: &[&str] = &["category"];
+
+// A valid CSV row, category is None when the file leaves it empty
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub date: NaiveDate,
+    pub amount: Money,
+    pub item: String,
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidRow {
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedImport {
+    pub rows: Vec<ImportRow>,
+    pub invalid: Vec<InvalidRow>,
+}
+
+// Rows waiting for the user to confirm the preview
+#[derive(Debug, Clone)]
+pub struct PendingImport {
+    batch: ImportBatch,
+    tool_name: &'static str,
+}
+
+struct Columns {
+    date: usize,
+    amount: usize,
+    description: usize,
+    category: Option<usize>,
+}
+
+impl Columns {
+    // Columns named in a header row, or None if the row is data
+    fn from_header(row: &[String]) -> Option<Self> {
+        let find = |names: &[&str]| {
+            row.iter()
+                .position(|cell| names.contains(&cell.trim().to_lowercase().as_str()))
+        };
+        Some(Self {
+            date: find(DATE_HEADERS)?,
+            amount: find(AMOUNT_HEADERS)?,
+            description: find(DESCRIPTION_HEADERS)?,
+            category: find(CATEGORY_HEADERS),
+        })
+    }
+
+    // Files without a header are read as date, amount, description, category
+    fn positional() -> Self {
+        Self {
+            date: 0,
+            amount: 1,
+            description: 2,
+            category: Some(3),
+        }
+    }
+}
+
+// Rows of the first sheet of an Excel file, or of a UTF-8 CSV file
+pub fn read_rows(content: &[u8]) -> Result<Vec<Vec<String>>, String> {
+    if !content.starts_with(XLSX_MAGIC) && !content.starts_with(XLS_MAGIC) {
+        let text = std::str::from_utf8(content)
+            .map_err(|_| "The file must be a UTF-8 encoded CSV or an Excel file".to_string())?;
+        return Ok(csv::parse(text));
+    }
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(content))
+        .map_err(|e| format!("Could not read the spreadsheet: {}", e))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| "The spreadsheet has no sheets".to_string())?
+        .map_err(|e| format!("Could not read the spreadsheet: {}", e))?;
+    Ok(range
+        .rows()
+        .map(|row| row.iter().map(cell_text).collect::<Vec<_>>())
+        .filter(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+        .collect())
+}
+
+// Spreadsheet dates become ISO dates, everything else its displayed text
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::DateTime(_) | Data::DateTimeIso(_) => cell
+            .as_date()
+            .map(|date| date.to_string())
+            .unwrap_or_else(|| cell.to_string()),
+        Data::Float(value) => format!("{:.2}", value),
+        _ => cell.to_string(),
+    }
+}
+
+// Parses rows of an uploaded expenses file. Errors only when the file as a whole is
+// unusable, individual bad rows are reported in ParsedImport.invalid.
+pub fn parse_expense_rows(rows: &[Vec<String>]) -> Result<ParsedImport, String> {
+    let Some(first) = rows.first() else {
+        return Err("The file is empty".to_string());
+    };
+    let (columns, skip) = match Columns::from_header(first) {
+        Some(columns) => (columns, 1),
+        None => (Columns::positional(), 0),
+    };
+    if rows.len() - skip > MAX_IMPORT_ROWS {
+        return Err(format!(
+            "The file has {} rows, at most {} can be imported at once",
+            rows.len() - skip,
+            MAX_IMPORT_ROWS
+        ));
+    }
+
+    let mut parsed = ParsedImport::default();
+    for (idx, row) in rows.iter().enumerate().skip(skip) {
+        match parse_row(row, &columns) {
+            Ok(row) => parsed.rows.push(row),
+            Err(reason) => parsed.invalid.push(InvalidRow {
+                line: idx + 1,
+                reason,
+            }),
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_row(row: &[String], columns: &Columns) -> Result<ImportRow, String> {
+    let cell = |idx: usize| row.get(idx).map(|c| c.trim()).unwrap_or("");
+
+    let date_text = cell(columns.date);
+    let date = parse_date(date_text).ok_or_else(|| format!("invalid date '{}'", date_text))?;
+    let amount_text = cell(columns.amount);
+    let amount = amount_text
+        .parse::<Money>()
+        .ok()
+        .filter(|a| !a.is_zero() && !a.is_negative())
+        .ok_or_else(|| format!("invalid amount '{}'", amount_text))?;
+    let item = cell(columns.description);
+    if item.is_empty() {
+        return Err("missing description".to_string());
+    }
+    let category = columns
+        .category
+        .map(cell)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string);
+
+    Ok(ImportRow {
+        date,
+        amount,
+        item: item.to_string(),
+        category,
+    })
+}
+
+impl RequestFulfilment {
+    // Validates an uploaded expenses file or bank statement, fills in missing
+    // categories and keeps the rows pending until the user confirms the preview
+    pub async fn prepare_import(
+        &self,
+        content: &[u8],
+        ctx: &SessionContext,
+    ) -> Result<ImportPreview, RequestError> {
+        let rows = read_rows(content).map_err(RequestError::InvalidRequest)?;
+        if let Some(statement) = parse_statement(&rows) {
+            return self.prepare_statement_import(statement, ctx).await;
+        }
+
+        let parsed = parse_expense_rows(&rows).map_err(RequestError::InvalidRequest)?;
+        if parsed.rows.is_empty() {
+            return Ok(no_valid_rows(&parsed.invalid));
+        }
+        let expenses = self.categorize_rows(&parsed.rows, ctx).await?;
+        let batch = ImportBatch {
+            expenses: expenses
+                .into_iter()
+                .map(|args| ImportEntry {
+                    args,
+                    import_hash: None,
+                })
+                .collect(),
+            income: Vec::new(),
+        };
+        Ok(self.store_pending(batch, "import_csv", &parsed.invalid, 0, ctx))
+    }
+
+    // Debits become expenses categorised by merchant, credits income.
+    // Rows imported before are left out by their hash.
+    async fn prepare_statement_import(
+        &self,
+        statement: ParsedStatement,
+        ctx: &SessionContext,
+    ) -> Result<ImportPreview, RequestError> {
+        if statement.rows.is_empty() {
+            return Ok(no_valid_rows(&statement.invalid));
+        }
+        let imported = self
+            .database
+            .get_import_hashes(ctx.user_id)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        let (new_rows, known_rows): (Vec<_>, Vec<_>) = statement
+            .rows
+            .into_iter()
+            .partition(|row| !imported.contains(&row.hash));
+        if new_rows.is_empty() {
+            return Ok(ImportPreview {
+                response: format!(
+                    "All {} transactions in this statement were already imported",
+                    known_rows.len()
+                ),
+                pending: false,
+            });
+        }
+
+        let (debits, credits): (Vec<_>, Vec<_>) = new_rows
+            .into_iter()
+            .partition(|row| row.direction == Direction::Debit);
+        let debit_rows: Vec<ImportRow> = debits
+            .iter()
+            .map(|row| ImportRow {
+                date: row.date,
+                amount: row.amount,
+                item: row.merchant.clone(),
+                category: None,
+            })
+            .collect();
+        let expenses = self.categorize_rows(&debit_rows, ctx).await?;
+        let account = self.statement_account(ctx).await?;
+        let batch = ImportBatch {
+            expenses: expenses
+                .into_iter()
+                .zip(&debits)
+                .map(|(args, row)| ImportEntry {
+                    args: AddExpenseArgs {
+                        account: Some(account.clone()),
+                        ..args
+                    },
+                    import_hash: Some(row.hash.clone()),
+                })
+                .collect(),
+            income: credits
+                .into_iter()
+                .map(|row| ImportEntry {
+                    args: AddIncomeArgs {
+                        amount: row.amount,
+                        source: row.merchant,
+                        category: income_category(&row.narration).to_string(),
+                        date: row.date,
+                        account: Some(account.clone()),
+                    },
+                    import_hash: Some(row.hash),
+                })
+                .collect(),
+        };
+        Ok(self.store_pending(
+            batch,
+            "import_statement",
+            &statement.invalid,
+            known_rows.len(),
+            ctx,
+        ))
+    }
+
+    // Statement rows belong to the user's first bank account, a "Bank" account is
+    // created when there is none yet
+    async fn statement_account(&self, ctx: &SessionContext) -> Result<String, RequestError> {
+        let accounts = self
+            .database
+            .get_accounts(ctx.user_id)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        if let Some(account) = accounts.into_iter().find(|a| a.kind == AccountKind::Bank) {
+            return Ok(account.name);
+        }
+        self.database
+            .add_account(ctx.user_id, STATEMENT_ACCOUNT, AccountKind::Bank)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        Ok(STATEMENT_ACCOUNT.to_string())
+    }
+
+    fn store_pending(
+        &self,
+        batch: ImportBatch,
+        tool_name: &'static str,
+        invalid: &[InvalidRow],
+        duplicates: usize,
+        ctx: &SessionContext,
+    ) -> ImportPreview {
+        let response = format_preview(&batch, invalid, duplicates);
+        self.pending_imports.insert(
+            (ctx.user_id, ctx.acting_member()),
+            PendingImport { batch, tool_name },
+        );
+        ImportPreview {
+            response,
+            pending: true,
+        }
+    }
+
+    pub async fn confirm_import(&self, ctx: &SessionContext) -> Result<String, RequestError> {
+        let Some(pending) = self
+            .pending_imports
+            .get(&(ctx.user_id, ctx.acting_member()))
+        else {
+            return Ok("This import has expired, please send the file again".to_string());
+        };
+        let counts = self
+            .database
+            .import_batch(&pending.batch, ctx, pending.tool_name)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        self.pending_imports
+            .remove(&(ctx.user_id, ctx.acting_member()));
+
+        let mut response = format!("📥 Imported {} expenses", counts.expenses);
+        if counts.income > 0 {
+            response.push_str(&format!(" and {} income entries", counts.income));
+        }
+        if counts.duplicates > 0 {
+            response.push_str(&format!(
+                "\n{} rows were skipped as already imported",
+                counts.duplicates
+            ));
+        }
+        Ok(response)
+    }
+
+    pub fn cancel_import(&self, ctx: &SessionContext) -> String {
+        self.pending_imports
+            .remove(&(ctx.user_id, ctx.acting_member()));
+        "Import cancelled".to_string()
+    }
+
+    // Categories from the file are kept, otherwise the category of a past expense
+    // for the same item, then local rules, then the LLM, then Uncategorized
+    async fn categorize_rows(
+        &self,
+        rows: &[ImportRow],
+        ctx: &SessionContext,
+    ) -> Result<Vec<AddExpenseArgs>, RequestError> {
+        let categories = self
+            .database
+            .get_categories(ctx.user_id)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        let mut item_categories = self
+            .database
+            .get_item_categories(ctx.user_id)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        // Match the casing of existing categories, e.g. "food" -> "Food"
+        let known_category = |category: &str| {
+            categories
+                .iter()
+                .find(|c| c.eq_ignore_ascii_case(category))
+                .cloned()
+        };
+        // Category names used as items, then rules for well known merchants
+        let local_category = |item: &str| {
+            known_category(item).or_else(|| {
+                merchant_category(item).map(|c| known_category(c).unwrap_or(c.to_string()))
+            })
+        };
+
+        let mut unknown_items: Vec<String> = rows
+            .iter()
+            .filter(|row| row.category.is_none())
+            .map(|row| row.item.to_lowercase())
+            .filter(|item| !item_categories.contains_key(item) && local_category(item).is_none())
+            .collect();
+        unknown_items.sort();
+        unknown_items.dedup();
+        for chunk in unknown_items.chunks(LLM_CATEGORIZE_BATCH) {
+            match self.llm_service.categorize(chunk, &categories).await {
+                Ok(assigned) => item_categories.extend(assigned),
+                Err(e) => error!(error = %e, "Could not categorize imported items"),
+            }
+        }
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let item = row.item.to_lowercase();
+                let category = match &row.category {
+                    Some(category) => known_category(category).unwrap_or(category.clone()),
+                    None => item_categories
+                        .get(&item)
+                        .cloned()
+                        .or_else(|| local_category(&item))
+                        .unwrap_or_else(|| UNCATEGORIZED.to_string()),
+                };
+                AddExpenseArgs {
+                    amount: row.amount,
+                    // Same "<amount> <item>" form as descriptions from messages
+                    description: format!("{} {}", row.amount.amount_string(), row.item),
+                    category,
+                    date: row.date,
+                    account: None,
+                }
+            })
+            .collect())
+    }
+}
+
+fn no_valid_rows(invalid: &[InvalidRow]) -> ImportPreview {
+    ImportPreview {
+        response: format!(
+            "No valid rows to import\n{}\n\n{}",
+            format_invalid_rows(invalid),
+            IMPORT_FORMAT_HINT
+        ),
+        pending: false,
+    }
+}
+
+fn format_preview(batch: &ImportBatch, invalid: &[InvalidRow], duplicates: usize) -> String {
+    let mut totals: Vec<(String, Money)> = Vec::new();
+    for entry in &batch.expenses {
+        let expense = &entry.args;
+        match totals.iter_mut().find(|(c, _)| *c == expense.category) {
+            Some((_, total)) => *total += expense.amount,
+            None => totals.push((expense.category.clone(), expense.amount)),
+        }
+    }
+    totals.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let rows = batch.expenses.len() + batch.income.len() + invalid.len() + duplicates;
+    let mut preview = format!("📥 {} rows, {} invalid", rows, invalid.len());
+    if duplicates > 0 {
+        preview.push_str(&format!(", {} already imported", duplicates));
+    }
+    let dates = batch
+        .expenses
+        .iter()
+        .map(|e| e.args.date)
+        .chain(batch.income.iter().map(|i| i.args.date));
+    if let (Some(first), Some(last)) = (dates.clone().min(), dates.max()) {
+        preview.push_str(&format!(
+            "\nFrom {} to {}",
+            first.format("%d/%m/%Y"),
+            last.format("%d/%m/%Y")
+        ));
+    }
+    if !batch.expenses.is_empty() {
+        preview.push_str(&format!(
+            "\n{} expenses, total {}",
+            batch.expenses.len(),
+            batch.expenses.iter().map(|e| e.args.amount).sum::<Money>()
+        ));
+    }
+    if let Some(account) = batch
+        .expenses
+        .iter()
+        .map(|e| &e.args.account)
+        .chain(batch.income.iter().map(|i| &i.args.account))
+        .find_map(|a| a.as_deref())
+    {
+        preview.push_str(&format!("\nAccount: {}", account));
+    }
+    if !batch.income.is_empty() {
+        preview.push_str(&format!(
+            "\n{} credits added as income, total {}",
+            batch.income.len(),
+            batch.income.iter().map(|i| i.args.amount).sum::<Money>()
+        ));
+    }
+    if !totals.is_empty() {
+        preview.push_str("\n\nTotals by category:");
+        for (category, total) in totals {
+            preview.push_str(&format!("\n{}: {}", category, total));
+        }
+    }
+    if !invalid.is_empty() {
+        preview.push_str("\n\n");
+        preview.push_str(&format_invalid_rows(invalid));
+    }
+    preview
+}
+
+fn format_invalid_rows(invalid: &[InvalidRow]) -> String {
+    let mut lines: Vec<String> = invalid
+        .iter()
+        .take(PREVIEW_INVALID_ROWS)
+        .map(|row| format!("Line {}: {}", row.line, row.reason))
+        .collect();
+    if invalid.len() > PREVIEW_INVALID_ROWS {
+        lines.push(format!(
+            "and {} more invalid rows",
+            invalid.len() - PREVIEW_INVALID_ROWS
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,54 @@
+use crate::core::Money;
+use crate::database::Expense;
+
+// Expenses shown on one page of a listing reply
+pub const LISTING_PAGE_SIZE: usize = 10;
+
+// Expenses listed oldest first, kept so a long listing can be paged through
+#[derive(Debug, Clone)]
+pub struct ExpenseListing {
+    pub title: String,
+    pub expenses: Vec<Expense>,
+    // Reply when nothing matched
+    pub empty_message: String,
+}
+
+impl ExpenseListing {
+    pub fn pages(&self) -> usize {
+        self.expenses.len().div_ceil(LISTING_PAGE_SIZE).max(1)
+    }
+
+    // One page of the listing, the running total carries over from earlier pages
+    pub fn render(&self, page: usize) -> String {
+        if self.expenses.is_empty() {
+            return self.empty_message.clone();
+        }
+        let page = page.min(self.pages() - 1);
+        let total: Money = self.expenses.iter().map(|e| e.amount).sum();
+        let count = self.expenses.len();
+        let mut text = format!(
+            "{}\nTotal: {} across {} {}\n\n",
+            self.title,
+            total,
+            count,
+            if count == 1 { "expense" } else { "expenses" }
+        );
+
+        let start = page * LISTING_PAGE_SIZE;
+        let mut running: Money = self.expenses[..start].iter().map(|e| e.amount).sum();
+        for expense in self.expenses.iter().skip(start).take(LISTING_PAGE_SIZE) {
+            running += expense.amount;
+            text.push_str(&format!(
+                "{} | {} | {} | {}\n",
+                expense.expense_date.format("%d/%m/%Y"),
+                expense.amount,
+                expense.description,
+                running
+            ));
+        }
+        if self.pages() > 1 {
+            text.push_str(&format!("\nPage {} of {}", page + 1, self.pages()));
+        }
+        text.trim_end().to_string()
+    }
+}
@@ -0,0 +1,191 @@
+use crate::configuration::{Context, LlmConfig};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+use thiserror::Error;
+use tracing::info;
+mod provider;
+
+pub use provider::{GroqProvider, LlmProvider, OpenAICompatibleProvider, ScriptedProvider};
+
+#[derive(Error, Debug)]
+pub enum LLMError {
+    #[error("Missing {0} in environment")]
+    MissingApiKey(String),
+
+    #[error("Failed to read system prompt: {0}")]
+    SystemPromptReadError(String),
+
+    #[error("LLM Inference API Error:{0}")]
+    APICallError(String),
+
+    #[error("Failed to parse LLM response: {0}")]
+    ResponseParseError(String),
+
+    #[error("Failed to read LLM script: {0}")]
+    ScriptReadError(String),
+}
+
+static TOOLS: OnceLock<Value> = OnceLock::new();
+
+const CATEGORIZE_PROMPT: &str = "You assign expense categories for a personal expense tracker. \
+     For every item in the user message call assign_categories once with one entry per item. \
+     Use one of the available categories whenever it fits and only create a new short category \
+     name (e.g. Grocery, Food, Transport, Utilities) when none does.";
+
+#[derive(Debug, Deserialize)]
+struct CategoryAssignment {
+    item: String,
+    category: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssignCategoriesArgs {
+    assignments: Vec<CategoryAssignment>,
+}
+
+fn categorize_tools() -> Value {
+    json!([{
+        "type": "function",
+        "function": {
+            "name": "assign_categories",
+            "description": "Assign a category to each expense item",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "assignments": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "item": {"type": "string", "description": "Item exactly as given"},
+                                "category": {"type": "string", "description": "Category name"}
+                            },
+                            "required": ["item", "category"]
+                        }
+                    }
+                },
+                "required": ["assignments"]
+            }
+        }
+    }])
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Debug)]
+pub struct LLMResponse {
+    pub tool_calls: Vec<ToolCall>,
+}
+
+fn get_tools() -> &'static Value {
+    TOOLS.get_or_init(|| {
+        serde_json::from_str(include_str!("../tools.json"))
+            .expect("Could not parse tools.json file")
+    })
+}
+
+pub struct LLMOrchestrator {
+    provider: Box<dyn LlmProvider>,
+    system_prompt: String,
+}
+
+impl LLMOrchestrator {
+    pub async fn new(context: &Context) -> Result<Self, LLMError> {
+        let provider: Box<dyn LlmProvider> = match &context.config.llm {
+            LlmConfig::Groq { model } => Box::new(GroqProvider::new(model)?),
+            LlmConfig::OpenaiCompatible {
+                base_url,
+                model,
+                api_key_env,
+            } => {
+                // API key is optional for local servers
+                let api_key = match api_key_env {
+                    Some(var) => {
+                        Some(env::var(var).map_err(|_| LLMError::MissingApiKey(var.clone()))?)
+                    }
+                    None => None,
+                };
+                Box::new(OpenAICompatibleProvider::new(base_url, api_key, model))
+            }
+            LlmConfig::Scripted { script_file } => {
+                Box::new(ScriptedProvider::from_file(script_file)?)
+            }
+        };
+
+        // Read system prompt from file
+        let system_prompt = fs::read_to_string("assets/llm/system_prompt.txt")
+            .map_err(|e| LLMError::SystemPromptReadError(e.to_string()))?;
+
+        Ok(Self::with_provider(provider, system_prompt))
+    }
+
+    pub fn with_provider(provider: Box<dyn LlmProvider>, system_prompt: String) -> Self {
+        Self {
+            provider,
+            system_prompt,
+        }
+    }
+
+    pub async fn try_parse(&self, request: &str) -> Result<LLMResponse, LLMError> {
+        let tools = get_tools();
+        info!("Request:{}", request);
+        let response = self
+            .provider
+            .complete(&self.system_prompt, request, tools)
+            .await?;
+        info!("tool calls:{:#?}", response.tool_calls);
+        Ok(response)
+    }
+
+    // Categories for expense items without one, keyed by the lowercased item.
+    // Items the model skips are simply missing from the map.
+    pub async fn categorize(
+        &self,
+        items: &[String],
+        categories: &[String],
+    ) -> Result<HashMap<String, String>, LLMError> {
+        let request = format!(
+            "AVAILABLE CATEGORIES: {}\n\nItems:\n{}",
+            categories.join(", "),
+            items
+                .iter()
+                .map(|item| format!("- {}", item))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        let response = self
+            .provider
+            .complete(CATEGORIZE_PROMPT, &request, &categorize_tools())
+            .await?;
+
+        let mut assigned = HashMap::new();
+        for tool_call in response.tool_calls {
+            if tool_call.function.name != "assign_categories" {
+                continue;
+            }
+            let args: AssignCategoriesArgs = serde_json::from_str(&tool_call.function.arguments)
+                .map_err(|e| LLMError::ResponseParseError(e.to_string()))?;
+            for assignment in args.assignments {
+                let category = assignment.category.trim();
+                if !category.is_empty() {
+                    assigned.insert(assignment.item.trim().to_lowercase(), category.to_string());
+                }
+            }
+        }
+        Ok(assigned)
+    }
+}
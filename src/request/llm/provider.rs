@@ -0,0 +1,168 @@
+use super::{LLMError, LLMResponse, ToolCall};
+use crate::core::RetryableClient;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+use tracing::info;
+
+const GROQ_BASE_URL: &str = "https://api.groq.com/openai/v1";
+const GROQ_API_KEY_VAR: &str = "GROQ_API_KEY";
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    async fn complete(
+        &self,
+        system_prompt: &str,
+        request: &str,
+        tools: &Value,
+    ) -> Result<LLMResponse, LLMError>;
+}
+
+// Any server exposing the OpenAI chat completions API (llama.cpp, Ollama, vLLM etc.)
+pub struct OpenAICompatibleProvider {
+    client: RetryableClient,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(base_url: &str, api_key: Option<String>, model: &str) -> Self {
+        Self {
+            client: RetryableClient::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAICompatibleProvider {
+    async fn complete(
+        &self,
+        system_prompt: &str,
+        request: &str,
+        tools: &Value,
+    ) -> Result<LLMResponse, LLMError> {
+        let mut request_builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .header("Content-Type", "application/json");
+        if let Some(api_key) = &self.api_key {
+            request_builder =
+                request_builder.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = self
+            .client
+            .execute_with_retry(request_builder.json(&json!({
+                "model": self.model,
+                "messages": [
+                    {
+                        "role": "system",
+                        "content": system_prompt
+                    },
+                    {
+                        "role": "user",
+                        "content": request
+                    }
+                ],
+                "tools": tools,
+                "tool_choice": "required",
+                "temperature": 0.0,
+                "max_completion_tokens": 8192
+            })))
+            .await
+            .map_err(|e| LLMError::APICallError(e.to_string()))?;
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ResponseParseError(e.to_string()))?;
+        info!("{}", serde_json::to_string_pretty(&body).unwrap());
+        let message = body["choices"][0]["message"].clone();
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|tc| serde_json::from_value(tc.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(LLMResponse { tool_calls })
+    }
+}
+
+pub struct GroqProvider {
+    inner: OpenAICompatibleProvider,
+}
+
+impl GroqProvider {
+    pub fn new(model: &str) -> Result<Self, LLMError> {
+        let api_key = env::var(GROQ_API_KEY_VAR)
+            .map_err(|_| LLMError::MissingApiKey(GROQ_API_KEY_VAR.to_string()))?;
+        Ok(Self {
+            inner: OpenAICompatibleProvider::new(GROQ_BASE_URL, Some(api_key), model),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GroqProvider {
+    async fn complete(
+        &self,
+        system_prompt: &str,
+        request: &str,
+        tools: &Value,
+    ) -> Result<LLMResponse, LLMError> {
+        self.inner.complete(system_prompt, request, tools).await
+    }
+}
+
+// Replays canned tool calls in order, cycling once the script is exhausted
+pub struct ScriptedProvider {
+    responses: Vec<Vec<ToolCall>>,
+    next: Mutex<usize>,
+}
+
+impl ScriptedProvider {
+    pub fn new(responses: Vec<Vec<ToolCall>>) -> Self {
+        Self {
+            responses,
+            next: Mutex::new(0),
+        }
+    }
+
+    // Script file is a JSON array of responses, each an array of tool calls in
+    // the same shape as the chat completions API returns them
+    pub fn from_file(script_file: &str) -> Result<Self, LLMError> {
+        let script = fs::read_to_string(script_file)
+            .map_err(|e| LLMError::ScriptReadError(e.to_string()))?;
+        let responses: Vec<Vec<ToolCall>> =
+            serde_json::from_str(&script).map_err(|e| LLMError::ScriptReadError(e.to_string()))?;
+        Ok(Self::new(responses))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ScriptedProvider {
+    async fn complete(
+        &self,
+        _system_prompt: &str,
+        _request: &str,
+        _tools: &Value,
+    ) -> Result<LLMResponse, LLMError> {
+        if self.responses.is_empty() {
+            return Ok(LLMResponse { tool_calls: vec![] });
+        }
+        let mut next = self.next.lock().unwrap();
+        let tool_calls = self.responses[*next % self.responses.len()].clone();
+        *next += 1;
+        Ok(LLMResponse { tool_calls })
+    }
+}
@@ -1,13 +1,34 @@
 use crate::configuration::Context;
+use crate::core::ExpirableCache;
+use crate::request::parser::FastPathParser;
 use crate::request::tools::ToolExecutor;
 use crate::{database::DatabaseService, request::llm::LLMOrchestrator};
+use chrono::Local;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tracing::{error, info};
+use types::args::{AddExpenseArgs, ReconcileBalanceArgs};
+mod digest;
+mod export;
+mod import;
+mod listing;
 mod llm;
+mod parser;
+mod statement;
 mod tools;
 pub mod types;
 mod visualization;
 
+pub use digest::build_digest;
+use export::{EXPORT_USAGE, ExportRequest};
+use import::PendingImport;
+use listing::ExpenseListing;
+pub use parser::description_item;
+pub use tools::{ToolError, not_owner_response};
+
+const CATEGORY_ALTERNATIVES: usize = 3;
+
 use types::*;
 
 #[derive(Error, Debug)]
@@ -23,11 +44,22 @@ pub enum RequestError {
 
     #[error("Database error: {0}")]
     DatabaseError(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
 }
 
+const PENDING_IMPORT_TTL: Duration = Duration::from_secs(3600);
+const LISTING_TTL: Duration = Duration::from_secs(86400);
+
 pub struct RequestFulfilment {
     pub llm_service: LLMOrchestrator,
     pub database: Arc<DatabaseService>,
+    // Validated import rows by (chat, member) waiting for the member who sent the file
+    // to confirm the preview
+    pending_imports: ExpirableCache<(i64, i64), PendingImport>,
+    // Long listings by (user, listing id) for paging through them from the reply
+    listings: ExpirableCache<(i64, i64), ExpenseListing>,
 }
 
 impl RequestFulfilment {
@@ -39,6 +71,8 @@ impl RequestFulfilment {
         Ok(RequestFulfilment {
             llm_service,
             database,
+            pending_imports: ExpirableCache::new(1000, PENDING_IMPORT_TTL),
+            listings: ExpirableCache::new(1000, LISTING_TTL),
         })
     }
 
@@ -52,6 +86,11 @@ impl RequestFulfilment {
             .get_categories(ctx.user_id)
             .await
             .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        let accounts = self
+            .database
+            .get_accounts(ctx.user_id)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
 
         let full_request = {
             let mut parts = Vec::new();
@@ -64,6 +103,24 @@ impl RequestFulfilment {
                 ));
             }
 
+            // Account names only matter once the user has more than the default one
+            if accounts.len() > 1 {
+                let accounts: Vec<String> = accounts
+                    .iter()
+                    .map(|a| {
+                        if a.is_default {
+                            format!("{} ({}, default)", a.name, a.kind.as_str())
+                        } else {
+                            format!("{} ({})", a.name, a.kind.as_str())
+                        }
+                    })
+                    .collect();
+                parts.push(format!(
+                    "ACCOUNTS: {}\nLeave the account out to use the default account.",
+                    accounts.join(", ")
+                ));
+            }
+
             // Add replied record context if exists
             if let Some(ref record_ctx) = ctx.replied_record {
                 parts.push(Self::format_record_context(record_ctx));
@@ -74,51 +131,336 @@ impl RequestFulfilment {
             parts.join("\n\n")
         };
 
+        // Try the deterministic parser first and only call the LLM when it cannot
+        // parse with confidence. Replies mostly need the LLM to interpret the record context.
+        let fast_path_response = if let Some(record) = &ctx.replied_record {
+            FastPathParser::parse_reply(request, record)
+        } else {
+            let item_categories = self
+                .database
+                .get_item_categories(ctx.user_id)
+                .await
+                .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+            FastPathParser::new(&categories, &item_categories, Local::now().date_naive())
+                .parse(request)
+        };
+
         // Get LLM response with tool calls
-        let llm_response = self.llm_service.try_parse(&full_request).await?;
+        let llm_response = match fast_path_response {
+            Some(response) => {
+                info!("Fast path parsed request: {}", request);
+                response
+            }
+            None => self.llm_service.try_parse(&full_request).await?,
+        };
 
         if llm_response.tool_calls.is_empty() {
             return Ok(FulfilmentResult {
                 response: "No action taken".to_string(),
                 finalize: None,
                 image: None,
+                category_choice: None,
+                reconciliation: None,
+                listing_page: None,
             });
         }
 
-        // Execute tool and generate response based on tool type
+        // Execute every tool call in order and combine the responses
         let tool_executor = ToolExecutor::new(self.database.clone());
-        let tool_call = &llm_response.tool_calls[0]; // Use first tool call
+        let mut responses = Vec::new();
+        let mut records = Vec::new();
+        let mut image = None;
+        let mut added_categories = Vec::new();
+        let mut reconciliations = Vec::new();
+        let mut listings = Vec::new();
+        let mut first_error = None;
+        let mut succeeded = 0;
 
-        let (record_id, response, image) = tool_executor
-            .execute_tool(&tool_call.function.name, &tool_call.function.arguments, ctx)
-            .await?;
-        // Generate response message based on tool
-        let finalize = match tool_call.function.name.as_str() {
-            "add_cash" => record_id.map(|id| FinalizeAction {
-                record_id: id,
-                action_type: ActionType::CashTransaction,
-            }),
-            "add_expense" => record_id.map(|id| FinalizeAction {
-                record_id: id,
-                action_type: ActionType::Expense,
-            }),
+        for tool_call in &llm_response.tool_calls {
+            // Listings are kept whole so a long one can be paged through later
+            let result = match tool_executor
+                .expense_listing(&tool_call.function.name, &tool_call.function.arguments, ctx)
+                .await
+            {
+                Ok(Some(listing)) => {
+                    let response = listing.render(0);
+                    listings.push(listing);
+                    Ok((None, response, None))
+                }
+                Ok(None) => {
+                    tool_executor
+                        .execute_tool(&tool_call.function.name, &tool_call.function.arguments, ctx)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok((record_id, response, tool_image)) => {
+                    let action_type = match tool_call.function.name.as_str() {
+                        "add_cash" => Some(ActionType::CashTransaction),
+                        "add_expense" | "split_expense" => Some(ActionType::Expense),
+                        "add_transfer" => Some(ActionType::Transfer),
+                        "add_income" => Some(ActionType::Income),
+                        "settle_up" => Some(ActionType::Settlement),
+                        _ => None,
+                    };
+                    // A reconciliation returns the account whose balance is off
+                    if tool_call.function.name == "reconcile_balance"
+                        && let Some(account_id) = record_id
+                        && let Ok(args) = serde_json::from_str::<ReconcileBalanceArgs>(
+                            &tool_call.function.arguments,
+                        )
+                    {
+                        reconciliations.push(Reconciliation {
+                            account_id,
+                            stated: args.amount,
+                        });
+                    }
+                    if let (Some(record_id), Some(action_type)) = (record_id, action_type) {
+                        if let ActionType::Expense = action_type
+                            && let Ok(args) = serde_json::from_str::<AddExpenseArgs>(
+                                &tool_call.function.arguments,
+                            )
+                        {
+                            added_categories.push((record_id, args.category));
+                        }
+                        records.push(CreatedRecord {
+                            record_id,
+                            action_type,
+                        });
+                    }
+                    if image.is_none() {
+                        image = tool_image;
+                    }
+                    responses.push(response);
+                    succeeded += 1;
+                }
+                Err(e) => {
+                    error!(tool = %tool_call.function.name, error = %e, "Tool execution failed");
+                    responses.push(format!(
+                        "❌ Could not complete {}",
+                        tool_call.function.name.replace('_', " ")
+                    ));
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        // Surface the error if nothing succeeded
+        if let Some(e) = first_error
+            && succeeded == 0
+        {
+            return Err(e.into());
+        }
+
+        // Only a reply for a single new expense is offered for category confirmation,
+        // as the whole message is replaced once a category is picked
+        let category_choice = match (responses.len(), added_categories.pop()) {
+            (1, Some((expense_id, category))) => {
+                Some(self.category_choice(expense_id, category, ctx).await)
+            }
+            _ => None,
+        };
+        // Same for an adjustment, the keyboard belongs to the whole message
+        let reconciliation = match responses.len() {
+            1 => reconciliations.pop(),
+            _ => None,
+        };
+        // And for paging, the request message identifies the listing
+        let listing_page = match (responses.len(), listings.pop()) {
+            (1, Some(listing)) if listing.pages() > 1 => {
+                let pages = listing.pages();
+                self.listings
+                    .insert((ctx.user_id, ctx.user_message_id), listing);
+                Some(ListingPage {
+                    listing_id: ctx.user_message_id,
+                    page: 0,
+                    pages,
+                })
+            }
             _ => None,
         };
 
+        let finalize = if records.is_empty() {
+            None
+        } else {
+            Some(FinalizeAction { records })
+        };
+
         Ok(FulfilmentResult {
-            response,
+            response: responses.join("\n"),
             finalize,
             image,
+            category_choice,
+            reconciliation,
+            listing_page,
         })
     }
 
+    // Another page of a listing reply, None once the listing has expired
+    pub fn listing_page(
+        &self,
+        listing_id: i64,
+        page: usize,
+        ctx: &SessionContext,
+    ) -> Option<(String, ListingPage)> {
+        let listing = self.listings.get(&(ctx.user_id, listing_id))?;
+        let page = page.min(listing.pages() - 1);
+        Some((
+            listing.render(page),
+            ListingPage {
+                listing_id,
+                page,
+                pages: listing.pages(),
+            },
+        ))
+    }
+
+    // Used by the /export command, args are the words after the command
+    pub async fn export(
+        &self,
+        args: &str,
+        ctx: &SessionContext,
+    ) -> Result<ExportFile, RequestError> {
+        let request = ExportRequest::parse(args)
+            .ok_or_else(|| RequestError::InvalidRequest(EXPORT_USAGE.to_string()))?;
+        export::build_export(&self.database, ctx.user_id, &request)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))
+    }
+
+    // Used by the /undo command, same as asking to "undo"
+    pub async fn undo_last_action(&self, ctx: &SessionContext) -> Result<String, RequestError> {
+        let tool_executor = ToolExecutor::new(self.database.clone());
+        let (_, response, _) = tool_executor
+            .execute_tool("undo_last_action", "{}", ctx)
+            .await?;
+        Ok(response)
+    }
+
+    async fn category_choice(
+        &self,
+        expense_id: i64,
+        category: String,
+        ctx: &SessionContext,
+    ) -> CategoryChoice {
+        // Confirmation still works without alternatives if the lookup fails
+        let alternatives = self
+            .database
+            .get_top_categories(ctx.user_id, CATEGORY_ALTERNATIVES as i64 + 1)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|c| !c.eq_ignore_ascii_case(&category))
+            .take(CATEGORY_ALTERNATIVES)
+            .collect();
+        CategoryChoice {
+            expense_id,
+            category,
+            alternatives,
+        }
+    }
+
+    // Applies a category picked from the confirmation keyboard of a bot message,
+    // returns the updated reply or None if the message no longer has that expense
+    pub async fn change_expense_category(
+        &self,
+        expense_id: i64,
+        category: &str,
+        bot_message_id: i64,
+        ctx: &SessionContext,
+    ) -> Result<Option<String>, RequestError> {
+        let expense = self
+            .database
+            .find_expenses_by_message(ctx.user_id, bot_message_id)
+            .await
+            .map_err(|e| RequestError::DatabaseError(e.to_string()))?
+            .into_iter()
+            .find(|e| e.id == expense_id);
+        let Some(expense) = expense else {
+            return Ok(None);
+        };
+        let tool_executor = ToolExecutor::new(self.database.clone());
+        Ok(Some(
+            tool_executor
+                .change_expense_category(&expense, category, ctx)
+                .await?,
+        ))
+    }
+
+    // Records the adjustment confirmed from a reconciliation keyboard, linked to that message
+    pub async fn confirm_reconciliation(
+        &self,
+        reconciliation: &Reconciliation,
+        ctx: &SessionContext,
+    ) -> Result<String, RequestError> {
+        let tool_executor = ToolExecutor::new(self.database.clone());
+        Ok(tool_executor.record_adjustment(reconciliation, ctx).await?)
+    }
+
+    // Reply to a reply about a message that added several entries, which cannot say on
+    // its own which entry is meant
+    pub fn choose_record_response(records: &[RecordContext]) -> String {
+        let entry = |record: &RecordContext| match record {
+            RecordContext::Expense(expense) => (
+                "expense",
+                expense.id,
+                format!(
+                    "{}, {} ({})",
+                    expense.amount, expense.description, expense.category
+                ),
+            ),
+            RecordContext::CashTransaction(cash) => (
+                "cash",
+                cash.id,
+                format!(
+                    "{} on {}",
+                    cash.amount,
+                    cash.transaction_date.format("%d/%m/%Y")
+                ),
+            ),
+            RecordContext::Income(income) => (
+                "income",
+                income.id,
+                format!("{} from {}", income.amount, income.source),
+            ),
+            RecordContext::Settlement(settlement) => (
+                "settlement",
+                settlement.id,
+                format!("{} with {}", settlement.amount.abs(), settlement.person),
+            ),
+            RecordContext::Transfer(transfer) => (
+                "transfer",
+                transfer.id,
+                format!(
+                    "{} from {} to {}",
+                    transfer.amount, transfer.from_account, transfer.to_account
+                ),
+            ),
+        };
+
+        let mut response = "This message has several entries, which one do you mean?\n".to_string();
+        for record in records {
+            let (kind, id, summary) = entry(record);
+            response.push_str(&format!("\n{} #{}: {}", kind, id, summary));
+        }
+        if let Some(record) = records.first() {
+            let (kind, id, _) = entry(record);
+            response.push_str(&format!(
+                "\n\nName it in a new message, e.g. \"delete {} #{}\"",
+                kind, id
+            ));
+        }
+        response
+    }
+
     fn format_record_context(record: &RecordContext) -> String {
         match record {
             RecordContext::Expense(expense) => {
                 format!(
                     "CONTEXT: The user is replying about an existing expense:\n\
                      - Expense ID: {}\n\
-                     - Amount: ₹{}\n\
+                     - Amount: {}\n\
                      - Description: {}\n\
                      - Category: {}\n\
                      - Date: {}",
@@ -126,16 +468,66 @@ impl RequestFulfilment {
                     expense.amount,
                     expense.description,
                     expense.category,
-                    expense.expense_date
+                    expense.expense_date.format("%d/%m/%Y")
                 )
             }
             RecordContext::CashTransaction(cash) => {
                 format!(
                     "CONTEXT: The user is replying about an existing cash transaction:\n\
                      - Transaction ID: {}\n\
-                     - Amount: ₹{}\n\
+                     - Amount: {}\n\
                      - Date: {}",
-                    cash.id, cash.amount, cash.transaction_date
+                    cash.id,
+                    cash.amount,
+                    cash.transaction_date.format("%d/%m/%Y")
+                )
+            }
+            RecordContext::Income(income) => {
+                format!(
+                    "CONTEXT: The user is replying about an existing income entry:\n\
+                     - Income ID: {}\n\
+                     - Amount: {}\n\
+                     - Source: {}\n\
+                     - Category: {}\n\
+                     - Date: {}",
+                    income.id,
+                    income.amount,
+                    income.source,
+                    income.category,
+                    income.income_date.format("%d/%m/%Y")
+                )
+            }
+            RecordContext::Settlement(settlement) => {
+                format!(
+                    "CONTEXT: The user is replying about an existing settlement with {}:\n\
+                     - Settlement ID: {}\n\
+                     - Amount: {}\n\
+                     - Direction: {}\n\
+                     - Date: {}",
+                    settlement.person,
+                    settlement.id,
+                    settlement.amount.abs(),
+                    if settlement.amount.is_negative() {
+                        "paid by the user"
+                    } else {
+                        "received by the user"
+                    },
+                    settlement.settlement_date.format("%d/%m/%Y")
+                )
+            }
+            RecordContext::Transfer(transfer) => {
+                format!(
+                    "CONTEXT: The user is replying about an existing transfer between accounts:\n\
+                     - Transfer ID: {}\n\
+                     - Amount: {}\n\
+                     - From: {}\n\
+                     - To: {}\n\
+                     - Date: {}",
+                    transfer.id,
+                    transfer.amount,
+                    transfer.from_account,
+                    transfer.to_account,
+                    transfer.transfer_date.format("%d/%m/%Y")
                 )
             }
         }
@@ -146,18 +538,38 @@ impl RequestFulfilment {
         action: FinalizeAction,
         bot_message_id: i64,
     ) -> Result<(), RequestError> {
-        match action.action_type {
-            ActionType::Expense => {
-                self.database
-                    .update_expense_bot_message(action.record_id, bot_message_id)
-                    .await
-                    .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
-            }
-            ActionType::CashTransaction => {
-                self.database
-                    .update_cash_bot_message(action.record_id, bot_message_id)
-                    .await
-                    .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+        for record in action.records {
+            match record.action_type {
+                ActionType::Expense => {
+                    self.database
+                        .update_expense_bot_message(record.record_id, bot_message_id)
+                        .await
+                        .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+                }
+                ActionType::CashTransaction => {
+                    self.database
+                        .update_cash_bot_message(record.record_id, bot_message_id)
+                        .await
+                        .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+                }
+                ActionType::Transfer => {
+                    self.database
+                        .update_transfer_bot_message(record.record_id, bot_message_id)
+                        .await
+                        .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+                }
+                ActionType::Income => {
+                    self.database
+                        .update_income_bot_message(record.record_id, bot_message_id)
+                        .await
+                        .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+                }
+                ActionType::Settlement => {
+                    self.database
+                        .update_settlement_bot_message(record.record_id, bot_message_id)
+                        .await
+                        .map_err(|e| RequestError::DatabaseError(e.to_string()))?;
+                }
             }
         }
         Ok(())
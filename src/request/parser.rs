@@ -0,0 +1,458 @@
+use super::llm::{FunctionCall, LLMResponse, ToolCall};
+use super::types::RecordContext;
+use crate::core::Money;
+use chrono::{Datelike, NaiveDate};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+const DATE_FORMAT: &str = "%d/%m/%Y";
+
+// Words which mean the message is more than a plain expense entry
+const RESERVED_WORDS: &[&str] = &[
+    "cash",
+    "balance",
+    "expense",
+    "expenses",
+    "category",
+    "categories",
+    "change",
+    "make",
+    "delete",
+    "show",
+    "from",
+    "to",
+    "this",
+    "last",
+    "yesterday",
+    "today",
+    "month",
+    "week",
+    "and",
+    "with",
+    "split",
+    "undo",
+    "salary",
+    "income",
+    "refund",
+];
+
+const BALANCE_FILLER_WORDS: &[&str] = &[
+    "show", "get", "check", "current", "my", "cash", "what", "is",
+];
+const CATEGORY_FILLER_WORDS: &[&str] = &["show", "list", "get", "all", "my"];
+const HISTORY_FILLER_WORDS: &[&str] = &["show", "view", "get", "its", "the", "change", "edit"];
+const UNDO_FILLER_WORDS: &[&str] = &["please", "that", "it", "the", "my", "last", "action"];
+// "i have 1230 in my wallet", "only 500 cash left in hand"
+const RECONCILE_FILLER_WORDS: &[&str] = &[
+    "i", "i've", "ive", "have", "has", "got", "only", "just", "now", "in", "my", "the", "left",
+    "on", "wallet", "cash", "hand",
+];
+
+const MAX_ITEM_WORDS: usize = 3;
+
+// Deterministic parser for regular messages, producing the same tool calls as the LLM.
+// Returns None whenever it is not confident so the request falls back to the LLM.
+pub struct FastPathParser<'a> {
+    categories: &'a [String],
+    item_categories: &'a HashMap<String, String>,
+    today: NaiveDate,
+}
+
+impl<'a> FastPathParser<'a> {
+    pub fn new(
+        categories: &'a [String],
+        item_categories: &'a HashMap<String, String>,
+        today: NaiveDate,
+    ) -> Self {
+        Self {
+            categories,
+            item_categories,
+            today,
+        }
+    }
+
+    pub fn parse(&self, request: &str) -> Option<LLMResponse> {
+        let words: Vec<&str> = request
+            .split_whitespace()
+            .map(|w| w.trim_end_matches(['?', '!']))
+            .filter(|w| !w.is_empty())
+            .collect();
+        if words.is_empty() {
+            return None;
+        }
+        let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+
+        let tool_call = if let Some(tool_call) = Self::parse_query(&lower) {
+            tool_call
+        } else if let Some(tool_call) = Self::parse_reconcile(&lower) {
+            tool_call
+        } else {
+            let (end, date) = self.split_date(&lower);
+            self.parse_cash(&lower[..end], date)
+                .or_else(|| self.parse_expense(&words[..end], &lower[..end], date))?
+        };
+
+        Some(LLMResponse {
+            tool_calls: vec![tool_call],
+        })
+    }
+
+    // Replies about an expense that need no interpretation, e.g. "show history"
+    pub fn parse_reply(request: &str, record: &RecordContext) -> Option<LLMResponse> {
+        let RecordContext::Expense(expense) = record else {
+            return None;
+        };
+        let words: Vec<String> = request
+            .split_whitespace()
+            .map(|w| w.trim_end_matches(['?', '!']).to_lowercase())
+            .collect();
+        if !Self::matches_with_filler(&words, "history", HISTORY_FILLER_WORDS) {
+            return None;
+        }
+        Some(LLMResponse {
+            tool_calls: vec![make_tool_call(
+                "get_expense_history",
+                json!({ "expense_id": expense.id }),
+            )],
+        })
+    }
+
+    fn parse_query(words: &[String]) -> Option<ToolCall> {
+        if Self::matches_with_filler(words, "balance", BALANCE_FILLER_WORDS)
+            || words == ["current", "cash"]
+        {
+            return Some(make_tool_call("get_balance", json!({})));
+        }
+        if Self::matches_with_filler(words, "categories", CATEGORY_FILLER_WORDS) {
+            return Some(make_tool_call("get_categories", json!({})));
+        }
+        if Self::matches_with_filler(words, "undo", UNDO_FILLER_WORDS) {
+            return Some(make_tool_call("undo_last_action", json!({})));
+        }
+        None
+    }
+
+    // A stated wallet amount: one amount, filler words and "have", "left" or "in my wallet"
+    // to tell it apart from "add cash 500" and an expense such as "wallet 500"
+    fn parse_reconcile(words: &[String]) -> Option<ToolCall> {
+        if !words.iter().any(|w| w == "have" || w == "left")
+            && !words.windows(3).any(|w| w == ["in", "my", "wallet"])
+        {
+            return None;
+        }
+        let mut amounts = words
+            .iter()
+            .filter(|w| !RECONCILE_FILLER_WORDS.contains(&w.as_str()));
+        let amount = parse_amount(amounts.next()?).filter(|a| !a.is_negative())?;
+        if amounts.next().is_some() {
+            return None;
+        }
+        Some(make_tool_call(
+            "reconcile_balance",
+            json!({ "amount": amount.amount_string() }),
+        ))
+    }
+
+    // True if the words are exactly the keyword surrounded by optional filler words
+    fn matches_with_filler(words: &[String], keyword: &str, filler: &[&str]) -> bool {
+        let mut rest = words.iter().filter(|w| !filler.contains(&w.as_str()));
+        rest.next().is_some_and(|w| w == keyword) && rest.next().is_none()
+    }
+
+    // Strips a trailing "on <date>" or "<date>", returning the remaining length and the date
+    fn split_date(&self, words: &[String]) -> (usize, NaiveDate) {
+        let len = words.len();
+        if len >= 2
+            && let Some(date) = parse_date(&words[len - 1])
+        {
+            if len >= 3 && words[len - 2] == "on" {
+                return (len - 2, date);
+            }
+            return (len - 1, date);
+        }
+        (len, self.today)
+    }
+
+    fn parse_cash(&self, words: &[String], date: NaiveDate) -> Option<ToolCall> {
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let amount = match words.as_slice() {
+            ["add", "cash", amount] | ["add", amount, "cash"] => parse_amount(amount)?,
+            ["cash", amount] | [amount, "cash"] => parse_amount(amount)?,
+            _ => return None,
+        };
+        if amount.is_zero() {
+            return None;
+        }
+        Some(make_tool_call(
+            "add_cash",
+            json!({
+                "amount": amount.amount_string(),
+                "date": date.format(DATE_FORMAT).to_string(),
+            }),
+        ))
+    }
+
+    fn parse_expense(&self, words: &[&str], lower: &[String], date: NaiveDate) -> Option<ToolCall> {
+        if words.len() < 2 {
+            return None;
+        }
+
+        // Either "<amount> [for] <item>" or "<item> <amount>"
+        let (amount_word, item_range) = if parse_amount(&lower[0]).is_some() {
+            let start = if lower[1] == "for" { 2 } else { 1 };
+            (words[0], start..words.len())
+        } else {
+            (words[words.len() - 1], 0..words.len() - 1)
+        };
+        let amount = parse_amount(&amount_word.to_lowercase())
+            .filter(|a| !a.is_zero() && !a.is_negative())?;
+
+        let item_words = &words[item_range.clone()];
+        let item_lower = &lower[item_range];
+        if item_words.is_empty()
+            || item_words.len() > MAX_ITEM_WORDS
+            || item_lower.iter().any(|w| {
+                RESERVED_WORDS.contains(&w.as_str()) || !w.chars().all(char::is_alphabetic)
+            })
+        {
+            return None;
+        }
+
+        let item = item_words.join(" ");
+        let category = self.pick_category(&item_lower.join(" "))?;
+
+        Some(make_tool_call(
+            "add_expense",
+            json!({
+                "amount": amount.amount_string(),
+                "description": format!("{} {}", amount_word.trim_start_matches('₹'), item),
+                "category": category,
+                "date": date.format(DATE_FORMAT).to_string(),
+            }),
+        ))
+    }
+
+    // Use the category of a past expense for the same item, or a category named like the item
+    fn pick_category(&self, item: &str) -> Option<String> {
+        if let Some(category) = self.item_categories.get(item) {
+            return Some(category.clone());
+        }
+        self.categories
+            .iter()
+            .find(|c| c.eq_ignore_ascii_case(item))
+            .cloned()
+    }
+}
+
+// Item part of an expense description, e.g. "fruits" for "500 fruits"
+pub fn description_item(description: &str) -> String {
+    let description = description.trim();
+    match description.split_once(' ') {
+        Some((amount, item)) if parse_amount(amount).is_some() => item.trim().to_lowercase(),
+        _ => description.to_lowercase(),
+    }
+}
+
+fn parse_amount(word: &str) -> Option<Money> {
+    word.parse::<Money>().ok()
+}
+
+// Accepts d.m.yy, d/m/yyyy, d-m-yy and similar, as well as ISO yyyy-mm-dd
+pub(crate) fn parse_date(word: &str) -> Option<NaiveDate> {
+    let parts: Vec<&str> = word.trim().split(['.', '/', '-']).collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let (day, month, year) = if parts[0].len() == 4 {
+        (parts[2], parts[1], parts[0])
+    } else {
+        (parts[0], parts[1], parts[2])
+    };
+    let day: u32 = day.parse().ok()?;
+    let month: u32 = month.parse().ok()?;
+    let year = match year.len() {
+        2 => 2000 + year.parse::<i32>().ok()?,
+        4 => year.parse::<i32>().ok()?,
+        _ => return None,
+    };
+    NaiveDate::from_ymd_opt(year, month, day).filter(|d| d.year() >= 2000)
+}
+
+fn make_tool_call(name: &str, arguments: Value) -> ToolCall {
+    ToolCall {
+        id: format!("fast_path_{}", name),
+        function: FunctionCall {
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()
+    }
+
+    // Name and arguments of the single tool call the parser produces
+    fn parse(request: &str) -> Option<(String, Value)> {
+        let categories = vec![
+            "Food".to_string(),
+            "Shopping".to_string(),
+            "Salary".to_string(),
+        ];
+        let item_categories = HashMap::from([
+            ("wallet".to_string(), "Shopping".to_string()),
+            ("fruits".to_string(), "Food".to_string()),
+        ]);
+        let response =
+            FastPathParser::new(&categories, &item_categories, today()).parse(request)?;
+        assert_eq!(response.tool_calls.len(), 1);
+        let function = &response.tool_calls[0].function;
+        Some((
+            function.name.clone(),
+            serde_json::from_str(&function.arguments).unwrap(),
+        ))
+    }
+
+    fn words(request: &str) -> Vec<String> {
+        request.split_whitespace().map(str::to_lowercase).collect()
+    }
+
+    #[test]
+    fn parses_queries() {
+        for (request, tool) in [
+            ("balance", "get_balance"),
+            ("show my balance?", "get_balance"),
+            ("current cash", "get_balance"),
+            ("list categories", "get_categories"),
+            ("undo that", "undo_last_action"),
+        ] {
+            assert_eq!(
+                parse(request),
+                Some((tool.to_string(), json!({}))),
+                "{}",
+                request
+            );
+        }
+    }
+
+    #[test]
+    fn parses_cash() {
+        assert_eq!(
+            parse("cash 500"),
+            Some((
+                "add_cash".to_string(),
+                json!({ "amount": "500", "date": "17/10/2026" })
+            ))
+        );
+        assert_eq!(
+            parse("add 1,500 cash on 12/10/26"),
+            Some((
+                "add_cash".to_string(),
+                json!({ "amount": "1500", "date": "12/10/2026" })
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_expenses() {
+        assert_eq!(
+            parse("500 fruits"),
+            Some((
+                "add_expense".to_string(),
+                json!({
+                    "amount": "500",
+                    "description": "500 fruits",
+                    "category": "Food",
+                    "date": "17/10/2026",
+                })
+            ))
+        );
+        assert_eq!(
+            parse("Fruits ₹49.50 15.10.2026"),
+            Some((
+                "add_expense".to_string(),
+                json!({
+                    "amount": "49.50",
+                    "description": "49.50 Fruits",
+                    "category": "Food",
+                    "date": "15/10/2026",
+                })
+            ))
+        );
+        let (_, arguments) = parse("200 for food").unwrap();
+        assert_eq!(arguments["description"], "200 food");
+        assert_eq!(arguments["category"], "Food");
+    }
+
+    #[test]
+    fn falls_back_when_unsure() {
+        for request in [
+            "",
+            "500",
+            "500 groceries",
+            "0 fruits",
+            "-50 fruits",
+            "500 fresh red juicy fruits",
+            "500 fruits2",
+            "fruits 500 600",
+            "cash 0",
+            "add cash",
+        ] {
+            assert_eq!(parse(request), None, "{}", request);
+        }
+    }
+
+    #[test]
+    fn reserved_words_fall_back() {
+        // "Salary" is a category, but the word means more than an expense
+        for request in ["500 salary", "food today 500", "fruits and wallet 500"] {
+            assert_eq!(parse(request), None, "{}", request);
+        }
+    }
+
+    #[test]
+    fn splits_trailing_date() {
+        let categories = Vec::new();
+        let item_categories = HashMap::new();
+        let parser = FastPathParser::new(&categories, &item_categories, today());
+        let date = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        assert_eq!(
+            parser.split_date(&words("500 fruits on 12/10/26")),
+            (2, date)
+        );
+        assert_eq!(parser.split_date(&words("500 fruits 12/10/26")), (2, date));
+        assert_eq!(parser.split_date(&words("500 fruits")), (2, today()));
+        assert_eq!(parser.split_date(&words("12/10/26")), (1, today()));
+    }
+
+    #[test]
+    fn parses_dates() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 12);
+        assert_eq!(parse_date("12/10/26"), date);
+        assert_eq!(parse_date("12.10.2026"), date);
+        assert_eq!(parse_date("12-10-26"), date);
+        assert_eq!(parse_date("2026-10-12"), date);
+        assert_eq!(parse_date("12/10/1999"), None);
+        assert_eq!(parse_date("1999-10-12"), None);
+        assert_eq!(parse_date("12/10/026"), None);
+        assert_eq!(parse_date("31/02/2026"), None);
+        assert_eq!(parse_date("12/10"), None);
+    }
+
+    #[test]
+    fn wallet_alone_is_an_expense_item() {
+        for request in ["wallet 500", "500 wallet"] {
+            let (name, arguments) = parse(request).unwrap();
+            assert_eq!(name, "add_expense", "{}", request);
+            assert_eq!(arguments["category"], "Shopping");
+        }
+        for request in ["i have 1230 in my wallet", "1230 in my wallet", "500 left"] {
+            let (name, _) = parse(request).unwrap();
+            assert_eq!(name, "reconcile_balance", "{}", request);
+        }
+    }
+}
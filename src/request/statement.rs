@@ -0,0 +1,413 @@
+use super::import::InvalidRow;
+use super::parser::parse_date;
+use crate::core::Money;
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+// Statements start with account details, the header is searched for in the first rows
+const HEADER_SEARCH_ROWS: usize = 30;
+const MAX_MERCHANT_LEN: usize = 40;
+
+// Header names after normalisation (lowercase, no dots or bracketed units), in order
+// of preference, e.g. the transaction date is used over the value date
+const DATE_HEADERS: &[&str] = &[
+    "txn date",
+    "transaction date",
+    "tran date",
+    "date",
+    "posting date",
+    "value date",
+    "value dt",
+];
+const NARRATION_HEADERS: &[&str] = &[
+    "narration",
+    "description",
+    "particulars",
+    "transaction remarks",
+    "transaction details",
+    "remarks",
+    "details",
+];
+const DEBIT_HEADERS: &[&str] = &[
+    "withdrawal amt",
+    "withdrawal amount",
+    "withdrawal",
+    "withdrawals",
+    "debit",
+    "debit amount",
+    "dr",
+];
+const CREDIT_HEADERS: &[&str] = &[
+    "deposit amt",
+    "deposit amount",
+    "deposit",
+    "deposits",
+    "credit",
+    "credit amount",
+    "cr",
+];
+const AMOUNT_HEADERS: &[&str] = &["amount", "transaction amount", "txn amount"];
+const TYPE_HEADERS: &[&str] = &[
+    "dr/cr",
+    "cr/dr",
+    "dr / cr",
+    "type",
+    "transaction type",
+    "debit/credit",
+];
+
+// Words in a narration that say how a payment was made rather than who was paid
+const NARRATION_NOISE: &[&str] = &[
+    "upi", "p2a", "p2m", "pos", "neft", "imps", "rtgs", "ach", "nach", "ecs", "vps", "ipay",
+    "payment", "pay", "paid", "received", "from", "via", "txn", "ref", "intent", "collect",
+    "request", "debit", "credit", "card", "bank",
+    // Bank codes that appear as separate fields in UPI narrations
+    "yesb", "icic", "hdfc", "sbin", "utib", "axis", "kkbk", "punb", "barb", "cnrb", "ubin", "idib",
+    "indb", "fdrl", "idfb", "airp", "pytm",
+];
+
+// Local rules for well known merchants, applied before asking the LLM
+const MERCHANT_CATEGORIES: &[(&str, &str)] = &[
+    ("swiggy", "Food"),
+    ("zomato", "Food"),
+    ("dominos", "Food"),
+    ("blinkit", "Grocery"),
+    ("zepto", "Grocery"),
+    ("bigbasket", "Grocery"),
+    ("instamart", "Grocery"),
+    ("dmart", "Grocery"),
+    ("uber", "Transport"),
+    ("ola", "Transport"),
+    ("rapido", "Transport"),
+    ("irctc", "Transport"),
+    ("redbus", "Transport"),
+    ("metro", "Transport"),
+    ("fastag", "Transport"),
+    ("indian oil", "Fuel"),
+    ("iocl", "Fuel"),
+    ("hpcl", "Fuel"),
+    ("bpcl", "Fuel"),
+    ("petrol", "Fuel"),
+    ("amazon", "Shopping"),
+    ("flipkart", "Shopping"),
+    ("myntra", "Shopping"),
+    ("ajio", "Shopping"),
+    ("meesho", "Shopping"),
+    ("netflix", "Entertainment"),
+    ("spotify", "Entertainment"),
+    ("hotstar", "Entertainment"),
+    ("bookmyshow", "Entertainment"),
+    ("airtel", "Utilities"),
+    ("jio", "Utilities"),
+    ("vodafone", "Utilities"),
+    ("bescom", "Utilities"),
+    ("electricity", "Utilities"),
+    ("broadband", "Utilities"),
+    ("apollo", "Health"),
+    ("pharmeasy", "Health"),
+    ("1mg", "Health"),
+    ("pharmacy", "Health"),
+];
+
+// Income categories for credits, matched against the whole narration
+const INCOME_CATEGORIES: &[(&str, &str)] = &[
+    ("salary", "Salary"),
+    ("payroll", "Salary"),
+    ("refund", "Refund"),
+    ("reversal", "Refund"),
+    ("cashback", "Cashback"),
+    ("interest", "Interest"),
+    ("int pd", "Interest"),
+    ("dividend", "Dividend"),
+];
+const OTHER_INCOME: &str = "Other income";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Debit,
+    Credit,
+}
+
+// A valid statement row, debits become expenses and credits income
+#[derive(Debug, Clone)]
+pub struct StatementRow {
+    pub date: NaiveDate,
+    pub amount: Money,
+    pub direction: Direction,
+    pub narration: String,
+    pub merchant: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedStatement {
+    pub rows: Vec<StatementRow>,
+    pub invalid: Vec<InvalidRow>,
+}
+
+enum AmountColumns {
+    // Separate withdrawal and deposit columns, e.g. HDFC, SBI, ICICI, Axis
+    DebitCredit { debit: usize, credit: usize },
+    // One amount column with a Dr/Cr or Debit/Credit marker, e.g. Kotak, UPI app exports
+    Typed { amount: usize, kind: usize },
+}
+
+struct StatementColumns {
+    date: usize,
+    narration: usize,
+    amounts: AmountColumns,
+}
+
+fn normalize_header(cell: &str) -> String {
+    let mut header = String::new();
+    let mut depth = 0;
+    for c in cell.to_lowercase().chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '.' => {}
+            _ if depth == 0 => header.push(c),
+            _ => {}
+        }
+    }
+    header.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl StatementColumns {
+    fn from_header(row: &[String]) -> Option<Self> {
+        let headers: Vec<String> = row.iter().map(|cell| normalize_header(cell)).collect();
+        let find = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.iter().position(|h| h == name))
+        };
+        let amounts = match (find(DEBIT_HEADERS), find(CREDIT_HEADERS)) {
+            (Some(debit), Some(credit)) if debit != credit => {
+                AmountColumns::DebitCredit { debit, credit }
+            }
+            _ => AmountColumns::Typed {
+                amount: find(AMOUNT_HEADERS)?,
+                kind: find(TYPE_HEADERS)?,
+            },
+        };
+        Some(Self {
+            date: find(DATE_HEADERS)?,
+            narration: find(NARRATION_HEADERS)?,
+            amounts,
+        })
+    }
+}
+
+// Reads rows as a bank or UPI statement, None if no statement header is found
+pub fn parse_statement(rows: &[Vec<String>]) -> Option<ParsedStatement> {
+    let (header_idx, columns) = rows
+        .iter()
+        .take(HEADER_SEARCH_ROWS)
+        .enumerate()
+        .find_map(|(idx, row)| StatementColumns::from_header(row).map(|c| (idx, c)))?;
+
+    let mut parsed = ParsedStatement::default();
+    // Identical rows in one statement are separate payments, their hashes are numbered
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for (idx, row) in rows.iter().enumerate().skip(header_idx + 1) {
+        match parse_statement_row(row, &columns) {
+            Ok(Some(mut row)) => {
+                let count = occurrences.entry(row.hash.clone()).or_insert(0);
+                *count += 1;
+                if *count > 1 {
+                    row.hash = row_hash(&format!("{}|{}", row.hash, count));
+                }
+                parsed.rows.push(row);
+            }
+            Ok(None) => {}
+            Err(reason) => parsed.invalid.push(InvalidRow {
+                line: idx + 1,
+                reason,
+            }),
+        }
+    }
+    Some(parsed)
+}
+
+// Ok(None) for rows without an amount, such as opening balance or footer lines
+fn parse_statement_row(
+    row: &[String],
+    columns: &StatementColumns,
+) -> Result<Option<StatementRow>, String> {
+    let cell = |idx: usize| row.get(idx).map(|c| c.trim()).unwrap_or("");
+
+    let (amount_text, direction) = match columns.amounts {
+        AmountColumns::DebitCredit { debit, credit } => {
+            match (parse_amount(cell(debit)), parse_amount(cell(credit))) {
+                (Some(Ok(_)), Some(Ok(_))) => {
+                    return Err("both debit and credit amounts".to_string());
+                }
+                (Some(_), _) => (cell(debit), Direction::Debit),
+                (_, Some(_)) => (cell(credit), Direction::Credit),
+                (None, None) => return Ok(None),
+            }
+        }
+        AmountColumns::Typed { amount, kind } => {
+            if parse_amount(cell(amount)).is_none() {
+                return Ok(None);
+            }
+            let direction = match cell(kind).to_lowercase().as_str() {
+                "dr" | "debit" | "d" | "paid" | "sent" => Direction::Debit,
+                "cr" | "credit" | "c" | "received" => Direction::Credit,
+                other => return Err(format!("unknown transaction type '{}'", other)),
+            };
+            (cell(amount), direction)
+        }
+    };
+    let amount = match parse_amount(amount_text) {
+        Some(Ok(amount)) => amount,
+        _ => return Err(format!("invalid amount '{}'", amount_text)),
+    };
+
+    let date_text = cell(columns.date);
+    let date =
+        parse_statement_date(date_text).ok_or_else(|| format!("invalid date '{}'", date_text))?;
+    let narration = cell(columns.narration)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if narration.is_empty() {
+        return Err("missing narration".to_string());
+    }
+
+    let signed_minor = match direction {
+        Direction::Debit => -amount.minor(),
+        Direction::Credit => amount.minor(),
+    };
+    let hash = row_hash(&format!(
+        "{}|{}|{}",
+        date,
+        signed_minor,
+        narration.to_lowercase()
+    ));
+    Ok(Some(StatementRow {
+        date,
+        amount,
+        direction,
+        merchant: merchant(&narration),
+        narration,
+        hash,
+    }))
+}
+
+// None for an empty or zero cell, amounts may carry a currency or a Dr/Cr suffix
+fn parse_amount(text: &str) -> Option<Result<Money, ()>> {
+    let mut text = text.trim().to_lowercase();
+    for marker in ["rs.", "rs", "inr", "₹", "dr", "cr"] {
+        text = text.replace(marker, "");
+    }
+    let text = text.trim();
+    if text.is_empty() || text == "-" {
+        return None;
+    }
+    match text.parse::<Money>() {
+        Ok(amount) if amount.is_zero() => None,
+        Ok(amount) if amount.is_negative() => Some(Ok(-amount)),
+        Ok(amount) => Some(Ok(amount)),
+        Err(_) => Some(Err(())),
+    }
+}
+
+// Statements use numeric dates, "01 Oct 2025", "01-Oct-25" or "Oct 01, 2025",
+// sometimes followed by a time
+fn parse_statement_date(text: &str) -> Option<NaiveDate> {
+    let first = text.split_whitespace().next()?;
+    if let Some(date) = parse_date(first) {
+        return Some(date);
+    }
+    let words: Vec<&str> = text.split_whitespace().take(3).collect();
+    let candidates = [first.to_string(), words.join(" ")];
+    const FORMATS: &[&str] = &[
+        "%d-%b-%Y",
+        "%d-%b-%y",
+        "%d %b %Y",
+        "%d %b %y",
+        "%b %d, %Y",
+        "%d %B %Y",
+    ];
+    candidates.iter().find_map(|candidate| {
+        FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(candidate, format).ok())
+    })
+}
+
+fn row_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Payee of a narration such as "UPI/DR/429812345678/SWIGGY/YESB/swiggy@ybl/UPI",
+// "POS 416021XXXXXX1234 AMAZON PAY INDIA" or "Paid to Zomato"
+pub fn merchant(narration: &str) -> String {
+    let mut vpa = None;
+    let mut found = None;
+    for part in narration.split(['/', '-', '*', ':', '|']) {
+        let part = part.trim();
+        if let Some((user, _)) = part.split_once('@') {
+            vpa.get_or_insert(user.trim_end_matches(|c: char| c.is_ascii_digit()));
+            continue;
+        }
+        let words: Vec<&str> = part
+            .split_whitespace()
+            .filter(|word| !is_noise(word))
+            .collect();
+        if !words.is_empty() {
+            found = Some(words.join(" "));
+            break;
+        }
+    }
+    let merchant = found
+        .or_else(|| vpa.filter(|v| !v.is_empty()).map(str::to_string))
+        .unwrap_or_else(|| narration.to_string())
+        .to_lowercase();
+    merchant.chars().take(MAX_MERCHANT_LEN).collect()
+}
+
+fn is_noise(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    let digits = word.chars().filter(|c| c.is_ascii_digit()).count();
+    // Short words are markers like DR, CR or ME rather than names
+    word.chars().count() <= 2
+        || NARRATION_NOISE.contains(&lower.as_str())
+        // Reference numbers, masked card numbers and IFSCs like HDFC0000001
+        || digits * 2 > word.len()
+        || lower.contains("xxxx")
+        || (word.len() == 11 && word.is_ascii() && lower[4..5] == *"0" && digits >= 4)
+}
+
+pub fn merchant_category(merchant: &str) -> Option<&'static str> {
+    keyword_category(merchant, MERCHANT_CATEGORIES)
+}
+
+pub fn income_category(narration: &str) -> &'static str {
+    keyword_category(narration, INCOME_CATEGORIES).unwrap_or(OTHER_INCOME)
+}
+
+// Single keywords match the start of a word, keywords with a space a run of words
+fn keyword_category(text: &str, rules: &[(&str, &'static str)]) -> Option<&'static str> {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let joined = words.join(" ");
+    rules
+        .iter()
+        .find(|(keyword, _)| {
+            if keyword.contains(' ') {
+                joined.contains(keyword)
+            } else {
+                words.iter().any(|w| w.starts_with(keyword))
+            }
+        })
+        .map(|(_, category)| *category)
+}
@@ -1,9 +1,40 @@
+use super::listing::ExpenseListing;
 use super::types::args::*;
 use super::visualization;
-use crate::{database::DatabaseService, request::SessionContext};
+use crate::{
+    core::{Currency, Money},
+    database::{
+        AccountBalance, CategorySummary, DEFAULT_ACCOUNT, DailyTotal, DatabaseError,
+        DatabaseService, DigestFrequency, Expense, HistoryChange, JournalAction,
+        RecurringFrequency, RecurringKind, RecurringStatus,
+    },
+    request::{
+        SessionContext, description_item,
+        types::{Reconciliation, RecordContext},
+    },
+};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime};
+use serde_json::{Map, Value};
 use std::sync::Arc;
 use thiserror::Error;
-use visualization::generate_pie_chart;
+use visualization::{
+    generate_bar_chart, generate_line_chart, generate_pie_chart, generate_stacked_bar_chart,
+};
+
+const MAX_DAILY_TREND_DAYS: i64 = 31;
+const BUDGET_WARNING_PERCENT: i64 = 80;
+// Category of the expense recorded when the user has less than the ledger says
+const UNACCOUNTED_CATEGORY: &str = "Unaccounted";
+// Names for the user that the LLM may include among the people of a split
+const SELF_NAMES: &[&str] = &["me", "myself", "i", "you", "self"];
+// Expense columns shown in history replies, in display order
+const HISTORY_FIELDS: &[&str] = &[
+    "amount_minor",
+    "description",
+    "category",
+    "expense_date",
+    "account",
+];
 
 #[derive(Error, Debug)]
 pub enum ToolError {
@@ -16,6 +47,21 @@ pub enum ToolError {
     #[error("Database error: {0}")]
     DatabaseError(String),
 
+    #[error("Unknown account: {0}")]
+    UnknownAccount(String),
+
+    #[error("Expense belongs to {0}")]
+    NotExpenseOwner(String),
+
+    #[error("Expense #{0} not found")]
+    ExpenseNotFound(i64),
+
+    #[error("Cash transaction #{0} not found")]
+    CashTransactionNotFound(i64),
+
+    #[error("Settlement #{0} not found")]
+    SettlementNotFound(i64),
+
     #[error("Visualization error: {0}")]
     VisualizationError(#[from] visualization::VisualizationError),
 }
@@ -34,26 +80,115 @@ impl ToolExecutor {
         tool_name: &str,
         arguments: &str,
         ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        // A mistyped account name is answered with the accounts the user does have
+        match self.execute(tool_name, arguments, ctx).await {
+            Err(ToolError::UnknownAccount(name)) => {
+                Ok((None, self.unknown_account_response(&name, ctx).await?, None))
+            }
+            Err(ToolError::NotExpenseOwner(owner)) => Ok((None, not_owner_response(&owner), None)),
+            Err(ToolError::ExpenseNotFound(expense_id)) => Ok((
+                None,
+                format!(
+                    "❌ Expense #{} not found, it may already be deleted",
+                    expense_id
+                ),
+                None,
+            )),
+            Err(ToolError::CashTransactionNotFound(transaction_id)) => Ok((
+                None,
+                format!(
+                    "❌ Cash transaction #{} not found, it may already be deleted",
+                    transaction_id
+                ),
+                None,
+            )),
+            Err(ToolError::SettlementNotFound(settlement_id)) => Ok((
+                None,
+                format!(
+                    "❌ Settlement #{} not found, it may already be deleted",
+                    settlement_id
+                ),
+                None,
+            )),
+            result => result,
+        }
+    }
+
+    async fn execute(
+        &self,
+        tool_name: &str,
+        arguments: &str,
+        ctx: &SessionContext,
     ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
         match tool_name {
             "add_cash" => {
-                let args: AddCashArgs = serde_json::from_str(arguments)
+                let mut args: AddCashArgs = serde_json::from_str(arguments)
                     .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                args.account = self.stored_account(args.account, ctx).await?;
+                let cash_id = self.add_cash(&args, ctx).await?;
+                let account = match &args.account {
+                    Some(account) if !account.eq_ignore_ascii_case(DEFAULT_ACCOUNT) => {
+                        account.clone()
+                    }
+                    _ => "cash balance".to_string(),
+                };
                 Ok((
-                    Some(self.add_cash(&args, ctx).await?),
-                    format!("✅ Added ₹{} to cash balance", args.amount),
+                    Some(cash_id),
+                    format!("✅ Added {} to {}", args.amount, account),
                     None,
                 ))
             }
             "add_expense" => {
-                let args: AddExpenseArgs = serde_json::from_str(arguments)
+                let mut args: AddExpenseArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                args.account = self.stored_account(args.account, ctx).await?;
+                let expense_id = self.add_expense(&args, ctx).await?;
+                Ok((
+                    Some(expense_id),
+                    self.expense_added_response(&args, ctx).await,
+                    None,
+                ))
+            }
+            "split_expense" => {
+                let mut args: SplitExpenseArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                args.account = self.stored_account(args.account, ctx).await?;
+                self.split_expense(args, ctx).await
+            }
+            "settle_up" => {
+                let mut args: SettleUpArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                args.account = self.stored_account(args.account, ctx).await?;
+                self.settle_up(args, ctx).await
+            }
+            "modify_settlement" => {
+                let args: ModifySettlementArgs = serde_json::from_str(arguments)
                     .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.database
+                    .modify_settlement(args, ctx)
+                    .await
+                    .map_err(database_error)?;
                 Ok((
-                    Some(self.add_expense(&args, ctx).await?),
-                    format!("✅ Added ₹{} under {}", args.amount, args.category),
+                    None,
+                    "✅ Settlement modified successfully".to_string(),
                     None,
                 ))
             }
+            "delete_settlement" => {
+                let args: DeleteSettlementArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.database
+                    .delete_settlement(args.settlement_id, ctx)
+                    .await
+                    .map_err(database_error)?;
+                Ok((None, "✅ Settlement deleted successfully".to_string(), None))
+            }
+            "get_split_summary" => {
+                let args: GetSplitSummaryArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.get_split_summary(args, ctx).await?, None))
+            }
             "modify_expense" => {
                 let args: ModifyExpenseArgs = serde_json::from_str(arguments)
                     .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
@@ -66,17 +201,235 @@ impl ToolExecutor {
                 self.delete_expense(args, ctx).await?;
                 Ok((None, "✅ Expense deleted successfully".to_string(), None))
             }
-            "get_balance" => Ok((None, self.get_balance(ctx).await?, None)),
+            "get_expense_history" => {
+                let args: GetExpenseHistoryArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.get_expense_history(args, ctx).await?, None))
+            }
+            "modify_cash" => {
+                let args: ModifyCashArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.modify_cash(args, ctx).await?;
+                Ok((
+                    None,
+                    "✅ Cash transaction modified successfully".to_string(),
+                    None,
+                ))
+            }
+            "delete_cash" => {
+                let args: DeleteCashArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.delete_cash(args, ctx).await?;
+                Ok((
+                    None,
+                    "✅ Cash transaction deleted successfully".to_string(),
+                    None,
+                ))
+            }
+            "add_transfer" => {
+                let mut args: AddTransferArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                args.from_account = self
+                    .database
+                    .account_name(ctx.user_id, Some(&args.from_account))
+                    .await
+                    .map_err(database_error)?;
+                args.to_account = self
+                    .database
+                    .account_name(ctx.user_id, Some(&args.to_account))
+                    .await
+                    .map_err(database_error)?;
+                if args.from_account.eq_ignore_ascii_case(&args.to_account) {
+                    return Ok((
+                        None,
+                        "A transfer needs two different accounts".to_string(),
+                        None,
+                    ));
+                }
+                let transfer_id = self
+                    .database
+                    .add_transfer(&args, ctx)
+                    .await
+                    .map_err(database_error)?;
+                Ok((
+                    Some(transfer_id),
+                    format!(
+                        "✅ Moved {} from {} to {}",
+                        args.amount, args.from_account, args.to_account
+                    ),
+                    None,
+                ))
+            }
+            "modify_transfer" => {
+                let args: ModifyTransferArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.database
+                    .modify_transfer(args, ctx)
+                    .await
+                    .map_err(database_error)?;
+                Ok((None, "✅ Transfer modified successfully".to_string(), None))
+            }
+            "delete_transfer" => {
+                let args: DeleteTransferArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.database
+                    .delete_transfer(args.transfer_id, ctx)
+                    .await
+                    .map_err(database_error)?;
+                Ok((None, "✅ Transfer deleted successfully".to_string(), None))
+            }
+            "add_account" => {
+                let args: AddAccountArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.add_account(args, ctx).await?, None))
+            }
+            "set_default_account" => {
+                let args: SetDefaultAccountArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                let account = self
+                    .database
+                    .set_default_account(ctx.user_id, &args.name)
+                    .await
+                    .map_err(database_error)?
+                    .ok_or(ToolError::UnknownAccount(args.name))?;
+                Ok((
+                    None,
+                    format!("✅ {} is now your default account", account),
+                    None,
+                ))
+            }
+            "add_income" => {
+                let mut args: AddIncomeArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                if args.amount.is_zero() || args.amount.is_negative() {
+                    return Ok((None, "Income must be a positive amount".to_string(), None));
+                }
+                args.account = self.stored_account(args.account, ctx).await?;
+                let income_id = self
+                    .database
+                    .add_income(&args, ctx)
+                    .await
+                    .map_err(database_error)?;
+                let mut response = format!(
+                    "✅ Added {} income from {} under {}",
+                    args.amount, args.source, args.category
+                );
+                if let Some(account) = args
+                    .account
+                    .as_ref()
+                    .filter(|a| !a.eq_ignore_ascii_case(DEFAULT_ACCOUNT))
+                {
+                    response.push_str(&format!(" to {}", account));
+                }
+                Ok((Some(income_id), response, None))
+            }
+            "modify_income" => {
+                let args: ModifyIncomeArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.database
+                    .modify_income(args, ctx)
+                    .await
+                    .map_err(database_error)?;
+                Ok((None, "✅ Income modified successfully".to_string(), None))
+            }
+            "delete_income" => {
+                let args: DeleteIncomeArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.database
+                    .delete_income(args.income_id, ctx)
+                    .await
+                    .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+                Ok((None, "✅ Income deleted successfully".to_string(), None))
+            }
+            "get_income_report" => {
+                let args: GetIncomeReportArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.get_income_report(args, ctx).await?, None))
+            }
+            "undo_last_action" => Ok((None, self.undo_last_action(ctx).await?, None)),
+            "get_balance" => {
+                let args: GetBalanceArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.get_balance(args, ctx).await?, None))
+            }
+            "reconcile_balance" => {
+                let args: ReconcileBalanceArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.reconcile_balance(args, ctx).await
+            }
             "get_expense_breakdown" => {
                 let args: GetExpenseBreakdownArgs = serde_json::from_str(arguments)
                     .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
                 self.get_expense_breakdown(args, ctx).await
             }
-            "get_category_expenses" => {
-                let args: GetCategoryExpensesArgs = serde_json::from_str(arguments)
+            "get_member_breakdown" => {
+                let args: GetMemberBreakdownArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.get_member_breakdown(args, ctx).await
+            }
+            "get_category_expenses" | "search_expenses" => {
+                let listing = self
+                    .expense_listing(tool_name, arguments, ctx)
+                    .await?
+                    .ok_or_else(|| ToolError::UnknownTool(tool_name.to_string()))?;
+                Ok((None, listing.render(0), None))
+            }
+            "get_category_bar_chart" => {
+                let args: GetCategoryBarChartArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.get_category_bar_chart(args, ctx).await
+            }
+            "get_spending_trend" => {
+                let args: GetSpendingTrendArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.get_spending_trend(args, ctx).await
+            }
+            "get_monthly_category_trend" => {
+                let args: GetMonthlyCategoryTrendArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                self.get_monthly_category_trend(args, ctx).await
+            }
+            "set_budget" => {
+                let args: SetBudgetArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.set_budget(args, ctx).await?, None))
+            }
+            "delete_budget" => {
+                let args: DeleteBudgetArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.delete_budget(args, ctx).await?, None))
+            }
+            "get_budget_status" => {
+                let args: GetBudgetStatusArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.get_budget_status(args, ctx).await?, None))
+            }
+            "add_recurring" => {
+                let args: AddRecurringArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.add_recurring(args, ctx).await?, None))
+            }
+            "list_recurring" => Ok((None, self.list_recurring(ctx).await?, None)),
+            "pause_recurring" | "resume_recurring" | "cancel_recurring" => {
+                let args: RecurringRuleArgs = serde_json::from_str(arguments)
                     .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
-                Ok((None, self.get_category_expenses(args, ctx).await?, None))
+                let status = match tool_name {
+                    "pause_recurring" => RecurringStatus::Paused,
+                    "resume_recurring" => RecurringStatus::Active,
+                    _ => RecurringStatus::Cancelled,
+                };
+                Ok((
+                    None,
+                    self.set_recurring_status(args, status, ctx).await?,
+                    None,
+                ))
             }
+            "set_digest" => {
+                let args: SetDigestArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                Ok((None, self.set_digest(args, ctx).await?, None))
+            }
+            "stop_digest" => Ok((None, self.stop_digest(ctx).await?, None)),
             "get_categories" => Ok((None, self.get_categories(ctx).await?, None)),
             _ => Err(ToolError::UnknownTool(tool_name.to_string())),
         }
@@ -86,7 +439,7 @@ impl ToolExecutor {
         self.database
             .add_cash_transaction(args, ctx)
             .await
-            .map_err(|e| ToolError::DatabaseError(e.to_string()))
+            .map_err(database_error)
     }
 
     async fn add_expense(
@@ -95,9 +448,9 @@ impl ToolExecutor {
         ctx: &SessionContext,
     ) -> Result<i64, ToolError> {
         self.database
-            .add_expense(args, ctx)
+            .add_expense(args, ctx, "add_expense")
             .await
-            .map_err(|e| ToolError::DatabaseError(e.to_string()))
+            .map_err(database_error)
     }
 
     async fn modify_expense(
@@ -106,9 +459,9 @@ impl ToolExecutor {
         ctx: &SessionContext,
     ) -> Result<(), ToolError> {
         self.database
-            .modify_expense(args, ctx)
+            .modify_expense(args, ctx, "modify_expense")
             .await
-            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+            .map_err(database_error)?;
 
         Ok(())
     }
@@ -119,20 +472,635 @@ impl ToolExecutor {
         ctx: &SessionContext,
     ) -> Result<(), ToolError> {
         self.database
-            .delete_expense(args.expense_id, ctx)
+            .delete_expense(args.expense_id, ctx, "delete_expense")
+            .await
+            .map_err(database_error)?;
+
+        Ok(())
+    }
+
+    async fn get_expense_history(
+        &self,
+        args: GetExpenseHistoryArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let history = self
+            .database
+            .get_expense_history(ctx.user_id, args.expense_id)
             .await
             .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        if history.is_empty() {
+            return Ok(format!("No history for expense #{}", args.expense_id));
+        }
+
+        let mut summary = format!("History of expense #{}\n", args.expense_id);
+        for entry in history {
+            let when = NaiveDateTime::parse_from_str(&entry.created_at, "%Y-%m-%d %H:%M:%S")
+                .map(|t| t.format("%d/%m/%Y %H:%M").to_string())
+                .unwrap_or(entry.created_at);
+            let details = match entry.change {
+                HistoryChange::Create => {
+                    let values = entry.new_values.unwrap_or_default();
+                    format!(
+                        "added: {}",
+                        HISTORY_FIELDS
+                            .iter()
+                            .filter_map(|column| history_value(column, &values))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }
+                HistoryChange::Modify => {
+                    let old = entry.old_values.unwrap_or_default();
+                    let new = entry.new_values.unwrap_or_default();
+                    let changes: Vec<String> = HISTORY_FIELDS
+                        .iter()
+                        .filter(|column| old.get(**column) != new.get(**column))
+                        .filter_map(|column| {
+                            Some(format!(
+                                "{} {} → {}",
+                                history_label(column),
+                                history_value(column, &old)?,
+                                history_value(column, &new)?
+                            ))
+                        })
+                        .collect();
+                    if changes.is_empty() {
+                        "changed, no visible difference".to_string()
+                    } else {
+                        format!("changed {}", changes.join(", "))
+                    }
+                }
+                HistoryChange::Delete => "deleted".to_string(),
+                HistoryChange::Restore => "restored".to_string(),
+            };
+            summary.push_str(&format!(
+                "\n{} {} ({})",
+                when,
+                details,
+                entry.tool_name.replace('_', " ")
+            ));
+        }
+        Ok(summary)
+    }
+
+    async fn modify_cash(
+        &self,
+        args: ModifyCashArgs,
+        ctx: &SessionContext,
+    ) -> Result<(), ToolError> {
+        self.database
+            .modify_cash_transaction(args, ctx)
+            .await
+            .map_err(database_error)?;
+
+        Ok(())
+    }
+
+    async fn delete_cash(
+        &self,
+        args: DeleteCashArgs,
+        ctx: &SessionContext,
+    ) -> Result<(), ToolError> {
+        self.database
+            .delete_cash_transaction(args.transaction_id, ctx)
+            .await
+            .map_err(database_error)?;
 
         Ok(())
     }
 
-    async fn get_balance(&self, ctx: &SessionContext) -> Result<String, ToolError> {
-        let balance = self
+    async fn undo_last_action(&self, ctx: &SessionContext) -> Result<String, ToolError> {
+        let undone = self
+            .database
+            .undo_last_action(ctx)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        let Some(undone) = undone else {
+            return Ok("Nothing to undo".to_string());
+        };
+
+        let record = match &undone.record {
+            RecordContext::Expense(expense) => format!(
+                "expense {} ({}, {}, {})",
+                expense.amount,
+                expense.description,
+                expense.category,
+                expense.expense_date.format("%d/%m/%Y")
+            ),
+            RecordContext::CashTransaction(cash) => format!(
+                "cash entry {} ({})",
+                cash.amount,
+                cash.transaction_date.format("%d/%m/%Y")
+            ),
+            RecordContext::Income(income) => format!(
+                "income {} ({}, {}, {})",
+                income.amount,
+                income.source,
+                income.category,
+                income.income_date.format("%d/%m/%Y")
+            ),
+            RecordContext::Settlement(settlement) => format!(
+                "settlement {} with {} ({})",
+                settlement.amount.abs(),
+                settlement.person,
+                settlement.settlement_date.format("%d/%m/%Y")
+            ),
+            RecordContext::Transfer(transfer) => format!(
+                "transfer {} from {} to {} ({})",
+                transfer.amount,
+                transfer.from_account,
+                transfer.to_account,
+                transfer.transfer_date.format("%d/%m/%Y")
+            ),
+        };
+        Ok(match undone.action {
+            JournalAction::Create => format!("↩️ Undone: removed {}", record),
+            JournalAction::Modify => format!("↩️ Undone: change reverted, {}", record),
+            JournalAction::Delete => format!("↩️ Undone: restored {}", record),
+        })
+    }
+
+    async fn get_balance(
+        &self,
+        args: GetBalanceArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        if let Some(name) = args.account {
+            let balance = self.account_balance(&name, ctx).await?;
+            return Ok(format!(
+                "{} balance: {}",
+                balance.account.name, balance.balance
+            ));
+        }
+        let balances = self
+            .database
+            .get_account_balances(ctx.user_id)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+
+        let total = self
             .database
             .get_balance(ctx.user_id)
             .await
             .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
-        Ok(format!("Cash balance: Rs.{}", balance))
+        if balances.len() <= 1 {
+            return Ok(format!("Cash balance: {}", total));
+        }
+        let mut summary = "Balances:".to_string();
+        for balance in &balances {
+            summary.push_str(&format!("\n{}: {}", balance.account.name, balance.balance));
+        }
+        summary.push_str(&format!("\n\nTotal: {}", total));
+        Ok(summary)
+    }
+
+    // Compares the amount the user has with the ledger, returning the account id when
+    // they differ so the adjustment can be offered for confirmation
+    async fn reconcile_balance(
+        &self,
+        args: ReconcileBalanceArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        if args.amount.is_negative() {
+            return Ok((
+                None,
+                "The amount you have can't be negative".to_string(),
+                None,
+            ));
+        }
+        let name = args.account.unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+        let balance = self.account_balance(&name, ctx).await?;
+        let account = &balance.account.name;
+        let difference = balance.balance - args.amount;
+
+        let response = if difference.is_zero() {
+            return Ok((
+                None,
+                format!("✅ {} balance matches: {}", account, balance.balance),
+                None,
+            ));
+        } else if difference.is_negative() {
+            format!(
+                "{} balance is {} but you have {}.\nThat is {} more than recorded, add it to {}?",
+                account, balance.balance, args.amount, -difference, account
+            )
+        } else {
+            format!(
+                "{} balance is {} but you have {}.\n{} is unaccounted for, record it under {}?",
+                account, balance.balance, args.amount, difference, UNACCOUNTED_CATEGORY
+            )
+        };
+        Ok((Some(balance.account.id), response, None))
+    }
+
+    // Brings the account to the stated amount: a shortfall becomes an Unaccounted
+    // expense and a surplus a cash entry
+    pub async fn record_adjustment(
+        &self,
+        reconciliation: &Reconciliation,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let balances = self
+            .database
+            .get_account_balances(ctx.user_id)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        let Some(balance) = balances
+            .into_iter()
+            .find(|b| b.account.id == reconciliation.account_id)
+        else {
+            return Ok("This account no longer exists".to_string());
+        };
+        let account = balance.account.name;
+        // Recomputed, entries may have been added since the question was asked
+        let difference = balance.balance - reconciliation.stated;
+        let date = Local::now().date_naive();
+
+        if difference.is_zero() {
+            return Ok(format!(
+                "✅ {} balance already matches: {}",
+                account, reconciliation.stated
+            ));
+        }
+        if difference.is_negative() {
+            let args = AddCashArgs {
+                amount: -difference,
+                date,
+                account: Some(account.clone()),
+            };
+            self.add_cash(&args, ctx).await?;
+            return Ok(format!(
+                "✅ Added {} to {}, balance is now {}",
+                args.amount, account, reconciliation.stated
+            ));
+        }
+        let args = AddExpenseArgs {
+            amount: difference,
+            description: format!("{} unaccounted", difference.amount_string()),
+            category: UNACCOUNTED_CATEGORY.to_string(),
+            date,
+            account: Some(account.clone()),
+        };
+        self.database
+            .add_expense(&args, ctx, "reconcile_balance")
+            .await
+            .map_err(database_error)?;
+        Ok(format!(
+            "✅ Recorded {} under {}, {} balance is now {}",
+            difference, UNACCOUNTED_CATEGORY, account, reconciliation.stated
+        ))
+    }
+
+    async fn account_balance(
+        &self,
+        name: &str,
+        ctx: &SessionContext,
+    ) -> Result<AccountBalance, ToolError> {
+        self.database
+            .get_account_balances(ctx.user_id)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?
+            .into_iter()
+            .find(|b| b.account.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| ToolError::UnknownAccount(name.to_string()))
+    }
+
+    async fn add_account(
+        &self,
+        args: AddAccountArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let name = args.name.trim();
+        if name.is_empty() {
+            return Ok("An account needs a name".to_string());
+        }
+        let created = self
+            .database
+            .add_account(ctx.user_id, name, args.kind)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        Ok(if created {
+            format!("✅ Added {} account {}", args.kind.as_str(), name)
+        } else {
+            format!("An account named {} already exists", name)
+        })
+    }
+
+    // The user pays the expense, each share is what one person owes them
+    async fn split_expense(
+        &self,
+        args: SplitExpenseArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        if args.amount.is_zero() || args.amount.is_negative() {
+            return Ok((
+                None,
+                "A split expense must be a positive amount".to_string(),
+                None,
+            ));
+        }
+        let mut people: Vec<String> = Vec::new();
+        for name in args
+            .people
+            .iter()
+            .chain(args.shares.iter().map(|share| &share.person))
+        {
+            let name = name.trim();
+            if name.is_empty() || SELF_NAMES.contains(&name.to_lowercase().as_str()) {
+                continue;
+            }
+            let name = self
+                .database
+                .person_name(ctx.user_id, name)
+                .await
+                .map_err(database_error)?;
+            if !people.iter().any(|p| p.eq_ignore_ascii_case(&name)) {
+                people.push(name);
+            }
+        }
+        if people.is_empty() {
+            return Ok((
+                None,
+                "Who should this expense be split with?".to_string(),
+                None,
+            ));
+        }
+
+        let fixed_share = |person: &str| {
+            args.shares
+                .iter()
+                .find(|share| share.person.trim().eq_ignore_ascii_case(person))
+                .map(|share| share.amount.minor())
+        };
+        let total = args.amount.minor();
+        let fixed_total: i64 = people.iter().filter_map(|p| fixed_share(p)).sum();
+        if fixed_total > total {
+            return Ok((
+                None,
+                format!("The shares add up to more than {}", args.amount),
+                None,
+            ));
+        }
+        // Whatever the fixed shares leave is split equally, rounding in the user's favour
+        let equal_ways = people.iter().filter(|p| fixed_share(p).is_none()).count() as i64
+            + i64::from(args.include_self);
+        let equal_share = if equal_ways > 0 {
+            (total - fixed_total) / equal_ways
+        } else {
+            0
+        };
+        let shares: Vec<(String, i64)> = people
+            .into_iter()
+            .map(|person| {
+                let weight = fixed_share(&person).unwrap_or(equal_share);
+                (person, weight)
+            })
+            .filter(|(_, weight)| *weight > 0)
+            .collect();
+
+        let expense_args = AddExpenseArgs {
+            amount: args.amount,
+            description: args.description,
+            category: args.category,
+            date: args.date,
+            account: args.account,
+        };
+        let expense_id = self
+            .database
+            .add_split_expense(&expense_args, &shares, total, ctx, "split_expense")
+            .await
+            .map_err(database_error)?;
+
+        let owed: Vec<String> = shares
+            .iter()
+            .map(|(person, weight)| {
+                format!(
+                    "{} owes you {}",
+                    person,
+                    Money::from_minor(*weight, args.amount.currency())
+                )
+            })
+            .collect();
+        Ok((
+            Some(expense_id),
+            format!(
+                "{}\n🤝 {}",
+                self.expense_added_response(&expense_args, ctx).await,
+                owed.join(", ")
+            ),
+            None,
+        ))
+    }
+
+    async fn settle_up(
+        &self,
+        args: SettleUpArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        let person = self
+            .database
+            .person_name(ctx.user_id, &args.person)
+            .await
+            .map_err(database_error)?;
+        let outstanding = self
+            .database
+            .get_person_balance(ctx.user_id, &person)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        let amount = args.amount.unwrap_or(outstanding).abs();
+        if amount.is_zero() {
+            return Ok((None, format!("You and {} are settled up", person), None));
+        }
+        let user_paid = args.user_paid.unwrap_or(outstanding.is_negative());
+        let signed = if user_paid { -amount } else { amount };
+        let settlement_id = self
+            .database
+            .add_settlement(&person, signed, args.date, args.account.as_deref(), ctx)
+            .await
+            .map_err(database_error)?;
+
+        let mut response = if user_paid {
+            format!("✅ You paid {} {}", person, amount)
+        } else {
+            format!("✅ {} paid you {}", person, amount)
+        };
+        if let Some(account) = args
+            .account
+            .as_ref()
+            .filter(|a| !a.eq_ignore_ascii_case(DEFAULT_ACCOUNT))
+        {
+            response.push_str(&format!(" via {}", account));
+        }
+        let remaining = outstanding - signed;
+        response.push_str(&if remaining.is_zero() {
+            ", all settled up".to_string()
+        } else if remaining.is_negative() {
+            format!(", you now owe {} {}", person, -remaining)
+        } else {
+            format!(", {} still owes you {}", person, remaining)
+        });
+        Ok((Some(settlement_id), response, None))
+    }
+
+    async fn get_split_summary(
+        &self,
+        args: GetSplitSummaryArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let Some(person) = args.person else {
+            let balances = self
+                .database
+                .get_split_balances(ctx.user_id)
+                .await
+                .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+            if balances.is_empty() {
+                return Ok("🤝 Everyone is settled up".to_string());
+            }
+            let mut summary = String::from("🤝 Who owes whom\n");
+            for balance in &balances {
+                summary.push_str(&format!(
+                    "{}\n",
+                    owes_line(&balance.person, balance.balance)
+                ));
+            }
+            let owed_to_you: Money = balances
+                .iter()
+                .map(|b| b.balance)
+                .filter(|b| !b.is_negative())
+                .sum();
+            let you_owe: Money = balances
+                .iter()
+                .map(|b| b.balance)
+                .filter(|b| b.is_negative())
+                .sum();
+            summary.push_str(&format!(
+                "\nOwed to you: {}\nYou owe: {}",
+                owed_to_you, -you_owe
+            ));
+            return Ok(summary);
+        };
+
+        let person = self
+            .database
+            .person_name(ctx.user_id, &person)
+            .await
+            .map_err(database_error)?;
+        let entries = self
+            .database
+            .get_split_entries(ctx.user_id, &person)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        if entries.is_empty() {
+            return Ok(format!("Nothing has been split with {}", person));
+        }
+        let mut summary = format!("🤝 Splits with {}\n", person);
+        for entry in &entries {
+            summary.push_str(&format!(
+                "{} {}: {}\n",
+                entry.date.format("%d/%m/%Y"),
+                entry.description,
+                entry.amount
+            ));
+        }
+        let balance: Money = entries.iter().map(|e| e.amount).sum();
+        summary.push_str(&format!("\n{}", owes_line(&person, balance)));
+        Ok(summary)
+    }
+
+    // Stored spelling of a named account, so replies match the account list
+    async fn stored_account(
+        &self,
+        account: Option<String>,
+        ctx: &SessionContext,
+    ) -> Result<Option<String>, ToolError> {
+        match account {
+            Some(name) => Ok(Some(
+                self.database
+                    .account_name(ctx.user_id, Some(&name))
+                    .await
+                    .map_err(database_error)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    async fn unknown_account_response(
+        &self,
+        name: &str,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let accounts = self
+            .database
+            .get_accounts(ctx.user_id)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        let names: Vec<String> = accounts.into_iter().map(|a| a.name).collect();
+        Ok(format!(
+            "❌ There is no account named {}. Your accounts: {}\nAdd it first, e.g. \"add {} bank account\"",
+            name,
+            names.join(", "),
+            name
+        ))
+    }
+
+    // Income against expenses per month with the savings rate, then income by category
+    async fn get_income_report(
+        &self,
+        args: GetIncomeReportArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let months = self
+            .database
+            .get_monthly_income_expense(ctx.user_id, args.start_date, args.end_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        if months.is_empty() {
+            return Ok("No income or expenses found for this period".to_string());
+        }
+        let breakdown = self
+            .database
+            .get_income_breakdown(ctx.user_id, args.start_date, args.end_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+
+        let mut summary = format!(
+            "Income vs expenses {} to {}\n",
+            args.start_date.format("%d/%m/%Y"),
+            args.end_date.format("%d/%m/%Y")
+        );
+        if months.len() > 1 {
+            for month in &months {
+                summary.push_str(&format!(
+                    "\n{}: in {}, out {}, {}",
+                    month.month.format("%b %Y"),
+                    month.income,
+                    month.expenses,
+                    savings_line(month.income, month.expenses)
+                ));
+            }
+            summary.push('\n');
+        }
+        let income = months.iter().map(|m| m.income).sum::<Money>();
+        let expenses = months.iter().map(|m| m.expenses).sum::<Money>();
+        summary.push_str(&format!("\nIncome: {}\nExpenses: {}", income, expenses));
+        let saved = income - expenses;
+        if saved.is_negative() {
+            summary.push_str(&format!("\nOverspent: {}", -saved));
+        } else {
+            summary.push_str(&format!("\nSaved: {}", saved));
+            if !income.is_zero() {
+                summary.push_str(&format!("\nSavings rate: {}%", percent_of(saved, income)));
+            }
+        }
+
+        if !breakdown.is_empty() {
+            summary.push_str("\n\nIncome by category:");
+            for item in &breakdown {
+                summary.push_str(&format!("\n{}: {}", item.category, item.total));
+            }
+        }
+        Ok(summary)
     }
 
     async fn get_expense_breakdown(
@@ -142,7 +1110,31 @@ impl ToolExecutor {
     ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
         let breakdown = self
             .database
-            .get_expense_breakdown(ctx.user_id, &args.start_date, &args.end_date)
+            .get_expense_breakdown(ctx.user_id, args.start_date, args.end_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+
+        if breakdown.is_empty() {
+            return Ok((None, "No expenses found for this period".to_string(), None));
+        }
+
+        let summary = Self::format_breakdown(&breakdown);
+
+        // Generate pie chart with legend
+        let chart_data = generate_pie_chart(&breakdown).ok();
+
+        Ok((None, summary, chart_data))
+    }
+
+    // Who spent how much in a shared group ledger
+    async fn get_member_breakdown(
+        &self,
+        args: GetMemberBreakdownArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        let breakdown = self
+            .database
+            .get_member_breakdown(ctx.user_id, args.start_date, args.end_date)
             .await
             .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
 
@@ -150,47 +1142,584 @@ impl ToolExecutor {
             return Ok((None, "No expenses found for this period".to_string(), None));
         }
 
-        let total: i64 = breakdown.iter().map(|s| s.total).sum();
+        let total: Money = breakdown.iter().map(|s| s.total).sum();
+        let mut summary = String::new();
+        for member in &breakdown {
+            summary.push_str(&format!(
+                "{} - {} ({}%)\n",
+                member.category,
+                member.total,
+                percent_of(member.total, total)
+            ));
+        }
+        summary.push_str(&format!("\nTotal: {}", total));
+        let chart_data = generate_pie_chart(&breakdown).ok();
+
+        Ok((None, summary, chart_data))
+    }
+
+    async fn get_category_bar_chart(
+        &self,
+        args: GetCategoryBarChartArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        let breakdown = self
+            .database
+            .get_expense_breakdown(ctx.user_id, args.start_date, args.end_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+
+        if breakdown.is_empty() {
+            return Ok((None, "No expenses found for this period".to_string(), None));
+        }
+
+        let summary = Self::format_breakdown(&breakdown);
+        let chart_data = generate_bar_chart(&breakdown).ok();
+
+        Ok((None, summary, chart_data))
+    }
+
+    async fn get_spending_trend(
+        &self,
+        args: GetSpendingTrendArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        let daily_totals = self
+            .database
+            .get_daily_totals(ctx.user_id, args.start_date, args.end_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+
+        if daily_totals.is_empty() {
+            return Ok((None, "No expenses found for this period".to_string(), None));
+        }
+
+        // Default to weekly points once a daily chart would get crowded
+        let granularity = args.granularity.unwrap_or(
+            if (args.end_date - args.start_date).num_days() > MAX_DAILY_TREND_DAYS {
+                TrendGranularity::Weekly
+            } else {
+                TrendGranularity::Daily
+            },
+        );
+        let (period_name, points) =
+            Self::bucket_totals(&daily_totals, args.start_date, args.end_date, granularity);
+
+        let total: Money = daily_totals.iter().map(|d| d.total).sum();
+        let average = Money::from_minor(total.minor() / points.len() as i64, total.currency());
+        let mut summary = format!(
+            "Spending from {} to {}\nTotal: {}\nAverage per {}: {}",
+            args.start_date.format("%d/%m/%Y"),
+            args.end_date.format("%d/%m/%Y"),
+            total,
+            period_name,
+            average
+        );
+        if let Some((label, highest)) = points.iter().max_by_key(|(_, total)| *total) {
+            summary.push_str(&format!(
+                "\nHighest {}: {} - {}",
+                period_name, label, highest
+            ));
+        }
+
+        let caption = format!(
+            "{} spending",
+            match granularity {
+                TrendGranularity::Daily => "Daily",
+                TrendGranularity::Weekly => "Weekly",
+            }
+        );
+        let chart_data = generate_line_chart(&caption, &points).ok();
+
+        Ok((None, summary, chart_data))
+    }
+
+    async fn get_monthly_category_trend(
+        &self,
+        args: GetMonthlyCategoryTrendArgs,
+        ctx: &SessionContext,
+    ) -> Result<(Option<i64>, String, Option<Vec<u8>>), ToolError> {
+        let monthly = self
+            .database
+            .get_monthly_category_totals(ctx.user_id, args.start_date, args.end_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+
+        if monthly.is_empty() {
+            return Ok((None, "No expenses found for this period".to_string(), None));
+        }
+
+        let mut summary = String::new();
+        let mut current_month = None;
+        for month_summary in monthly.iter() {
+            if current_month != Some(month_summary.month) {
+                if current_month.is_some() {
+                    summary.push('\n');
+                }
+                let month_total: Money = monthly
+                    .iter()
+                    .filter(|s| s.month == month_summary.month)
+                    .map(|s| s.total)
+                    .sum();
+                summary.push_str(&format!(
+                    "{} - {}\n",
+                    month_summary.month.format("%b %Y"),
+                    month_total
+                ));
+                current_month = Some(month_summary.month);
+            }
+            summary.push_str(&format!(
+                "  {} - {}\n",
+                month_summary.category, month_summary.total
+            ));
+        }
+
+        let chart_data = generate_stacked_bar_chart(&monthly).ok();
+
+        Ok((None, summary.trim_end().to_string(), chart_data))
+    }
+
+    fn format_breakdown(breakdown: &[CategorySummary]) -> String {
+        let total: Money = breakdown.iter().map(|s| s.total).sum();
         let mut summary = String::new();
 
         for category_expense in breakdown.iter() {
             summary.push_str(&format!(
-                "{} - Rs.{}\n",
+                "{} - {}\n",
                 category_expense.category, category_expense.total
             ));
         }
-        summary.push_str(&format!("\nTotal: Rs.{}", total));
+        summary.push_str(&format!("\nTotal: {}", total));
+        summary
+    }
 
-        // Generate pie chart with legend
-        let chart_data = generate_pie_chart(&breakdown).ok();
+    // Spread daily totals over every day or week in the range, filling gaps with zero
+    fn bucket_totals(
+        daily_totals: &[DailyTotal],
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        granularity: TrendGranularity,
+    ) -> (&'static str, Vec<(String, Money)>) {
+        let bucket_start = |date: NaiveDate| match granularity {
+            TrendGranularity::Daily => date,
+            TrendGranularity::Weekly => {
+                date - Duration::days(date.weekday().num_days_from_monday() as i64)
+            }
+        };
+        let step = match granularity {
+            TrendGranularity::Daily => Duration::days(1),
+            TrendGranularity::Weekly => Duration::weeks(1),
+        };
 
-        Ok((None, summary, chart_data))
+        let mut buckets: Vec<(NaiveDate, Money)> = Vec::new();
+        let mut date = bucket_start(start_date);
+        while date <= end_date {
+            buckets.push((date, Money::zero()));
+            date += step;
+        }
+        for daily in daily_totals {
+            let key = bucket_start(daily.date);
+            if let Some((_, total)) = buckets.iter_mut().find(|(d, _)| *d == key) {
+                *total += daily.total;
+            }
+        }
+
+        let period_name = match granularity {
+            TrendGranularity::Daily => "day",
+            TrendGranularity::Weekly => "week",
+        };
+        let points = buckets
+            .into_iter()
+            .map(|(date, total)| (date.format("%d %b").to_string(), total))
+            .collect();
+        (period_name, points)
+    }
+
+    // Listing for the tools that list individual expenses, None for any other tool
+    pub async fn expense_listing(
+        &self,
+        tool_name: &str,
+        arguments: &str,
+        ctx: &SessionContext,
+    ) -> Result<Option<ExpenseListing>, ToolError> {
+        let period = |start: NaiveDate, end: NaiveDate| {
+            format!("{} to {}", start.format("%d/%m/%Y"), end.format("%d/%m/%Y"))
+        };
+        let listing = match tool_name {
+            "get_category_expenses" => {
+                let args: GetCategoryExpensesArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                let expenses = self
+                    .database
+                    .get_category_expenses(
+                        ctx.user_id,
+                        &args.category,
+                        args.start_date,
+                        args.end_date,
+                    )
+                    .await
+                    .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+                let period = period(args.start_date, args.end_date);
+                ExpenseListing {
+                    title: format!("📋 {} expenses, {}", args.category, period),
+                    expenses,
+                    empty_message: format!("No {} expenses from {}", args.category, period),
+                }
+            }
+            "search_expenses" => {
+                let args: SearchExpensesArgs = serde_json::from_str(arguments)
+                    .map_err(|e| ToolError::ArgumentParseError(e.to_string()))?;
+                let expenses = self
+                    .database
+                    .search_expenses(ctx.user_id, &args.query, args.start_date, args.end_date)
+                    .await
+                    .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+                let period = match (args.start_date, args.end_date) {
+                    (Some(start), Some(end)) => format!(", {}", period(start, end)),
+                    (Some(start), None) => format!(" since {}", start.format("%d/%m/%Y")),
+                    (None, Some(end)) => format!(" until {}", end.format("%d/%m/%Y")),
+                    (None, None) => String::new(),
+                };
+                ExpenseListing {
+                    title: format!("🔎 Expenses matching \"{}\"{}", args.query, period),
+                    expenses,
+                    empty_message: format!(
+                        "No expenses found matching \"{}\"{}",
+                        args.query, period
+                    ),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(listing))
+    }
+
+    async fn set_budget(
+        &self,
+        args: SetBudgetArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        if args.amount.is_zero() || args.amount.is_negative() {
+            return Err(ToolError::ArgumentParseError(
+                "Budget amount must be positive".to_string(),
+            ));
+        }
+        self.database
+            .set_budget(ctx.user_id, &args.category, args.amount)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        Ok(format!(
+            "✅ {} budget set to {} a month",
+            args.category, args.amount
+        ))
     }
 
-    async fn get_category_expenses(
+    async fn delete_budget(
         &self,
-        args: GetCategoryExpensesArgs,
+        args: DeleteBudgetArgs,
         ctx: &SessionContext,
     ) -> Result<String, ToolError> {
-        let expenses = self
+        let deleted = self
             .database
-            .get_category_expenses(
-                ctx.user_id,
-                &args.category,
-                &args.start_date,
-                &args.end_date,
-            )
+            .delete_budget(ctx.user_id, &args.category)
             .await
             .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        if deleted {
+            Ok(format!("✅ {} budget removed", args.category))
+        } else {
+            Ok(format!("No budget set for {}", args.category))
+        }
+    }
 
-        let mut summary: String = "".to_string();
-        for expense in expenses {
-            summary.push_str(&expense.description);
-            summary.push('\n');
+    async fn get_budget_status(
+        &self,
+        args: GetBudgetStatusArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let budgets = match &args.category {
+            Some(category) => self
+                .database
+                .get_budget(ctx.user_id, category)
+                .await
+                .map_err(|e| ToolError::DatabaseError(e.to_string()))?
+                .into_iter()
+                .collect(),
+            None => self
+                .database
+                .get_budgets(ctx.user_id)
+                .await
+                .map_err(|e| ToolError::DatabaseError(e.to_string()))?,
+        };
+
+        if budgets.is_empty() {
+            return Ok(match args.category {
+                Some(category) => format!("No budget set for {}", category),
+                None => "No budgets set".to_string(),
+            });
+        }
+
+        let (start, end) = month_bounds(Local::now().date_naive());
+        let mut summary = format!("Budgets for {}\n", start.format("%B %Y"));
+        for budget in budgets {
+            let spent = self
+                .database
+                .get_category_total(ctx.user_id, &budget.category, start, end)
+                .await
+                .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+            let remaining = budget.amount - spent;
+            summary.push_str(&format!(
+                "\n{}: {} of {} spent ({}%), ",
+                budget.category,
+                spent,
+                budget.amount,
+                percent_of(spent, budget.amount)
+            ));
+            if remaining.is_negative() {
+                summary.push_str(&format!("over by {}", remaining.abs()));
+            } else {
+                summary.push_str(&format!("{} left", remaining));
+            }
         }
         Ok(summary)
     }
 
+    // Moves an expense to another category, returning the updated add_expense reply
+    pub async fn change_expense_category(
+        &self,
+        expense: &Expense,
+        category: &str,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let args = ModifyExpenseArgs {
+            expense_id: expense.id,
+            amount: None,
+            description: None,
+            category: Some(category.to_string()),
+            date: None,
+            account: None,
+        };
+        self.database
+            .modify_expense(args, ctx, "confirm_category")
+            .await
+            .map_err(database_error)?;
+
+        let args = AddExpenseArgs {
+            amount: expense.amount,
+            description: expense.description.clone(),
+            category: category.to_string(),
+            date: expense.expense_date,
+            account: Some(expense.account.clone()),
+        };
+        Ok(self.expense_added_response(&args, ctx).await)
+    }
+
+    async fn expense_added_response(&self, args: &AddExpenseArgs, ctx: &SessionContext) -> String {
+        let mut response = format!("✅ Added {} under {}", args.amount, args.category);
+        if let Some(account) = args
+            .account
+            .as_ref()
+            .filter(|a| !a.eq_ignore_ascii_case(DEFAULT_ACCOUNT))
+        {
+            response.push_str(&format!(" from {}", account));
+        }
+        // The expense is already recorded, a failed budget check only skips the warning
+        if let Ok(Some(alert)) = self.budget_alert(args, ctx).await {
+            response.push('\n');
+            response.push_str(&alert);
+        }
+        response
+    }
+
+    // Warning when this expense pushes the category past 80% or 100% of its monthly budget
+    async fn budget_alert(
+        &self,
+        args: &AddExpenseArgs,
+        ctx: &SessionContext,
+    ) -> Result<Option<String>, ToolError> {
+        let Some(budget) = self
+            .database
+            .get_budget(ctx.user_id, &args.category)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        let (start, end) = month_bounds(args.date);
+        let spent = self
+            .database
+            .get_category_total(ctx.user_id, &args.category, start, end)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        let spent_before = spent - args.amount;
+
+        let crossed = |percent: i64| {
+            spent_before.minor() * 100 < budget.amount.minor() * percent
+                && spent.minor() * 100 >= budget.amount.minor() * percent
+        };
+        if crossed(100) {
+            Ok(Some(format!(
+                "🚨 {} budget exceeded: {} of {} spent this month",
+                budget.category, spent, budget.amount
+            )))
+        } else if crossed(BUDGET_WARNING_PERCENT) {
+            Ok(Some(format!(
+                "⚠️ {}% of {} budget used: {} of {}, {} left",
+                percent_of(spent, budget.amount),
+                budget.category,
+                spent,
+                budget.amount,
+                budget.amount - spent
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn add_recurring(
+        &self,
+        mut args: AddRecurringArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        if args.amount.is_zero() {
+            return Err(ToolError::ArgumentParseError(
+                "Recurring amount must not be zero".to_string(),
+            ));
+        }
+        match args.kind {
+            RecurringKind::Expense | RecurringKind::Income => {
+                if args.amount.is_negative()
+                    || args.description.is_none()
+                    || args.category.is_none()
+                {
+                    return Err(ToolError::ArgumentParseError(
+                        "Recurring expenses and income need a positive amount, description and category"
+                            .to_string(),
+                    ));
+                }
+            }
+            RecurringKind::Cash => {
+                args.description = None;
+                args.category = None;
+            }
+        }
+
+        let rule_id = self
+            .database
+            .add_recurring_rule(&args, ctx)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        Ok(format!(
+            "🔁 Recurring rule #{} created: {}, first on {}",
+            rule_id,
+            describe_recurring(
+                args.kind,
+                args.amount,
+                args.description.as_deref(),
+                args.frequency
+            ),
+            args.start_date.format("%d/%m/%Y")
+        ))
+    }
+
+    async fn list_recurring(&self, ctx: &SessionContext) -> Result<String, ToolError> {
+        let rules = self
+            .database
+            .get_recurring_rules(ctx.user_id)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        if rules.is_empty() {
+            return Ok("No recurring rules".to_string());
+        }
+
+        let mut summary = "Recurring rules\n".to_string();
+        for rule in rules {
+            summary.push_str(&format!(
+                "\n#{} {}, ",
+                rule.id,
+                describe_recurring(
+                    rule.kind,
+                    rule.amount,
+                    rule.description.as_deref(),
+                    rule.frequency
+                )
+            ));
+            if rule.status == RecurringStatus::Paused {
+                summary.push_str("paused");
+            } else {
+                summary.push_str(&format!(
+                    "next on {}",
+                    rule.next_run_date.format("%d/%m/%Y")
+                ));
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn set_recurring_status(
+        &self,
+        args: RecurringRuleArgs,
+        status: RecurringStatus,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let updated = match status {
+            RecurringStatus::Active => {
+                self.database
+                    .resume_recurring_rule(ctx.user_id, args.rule_id, Local::now().date_naive())
+                    .await
+            }
+            _ => {
+                self.database
+                    .set_recurring_status(ctx.user_id, args.rule_id, status)
+                    .await
+            }
+        }
+        .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        if !updated {
+            return Ok(format!("No recurring rule #{}", args.rule_id));
+        }
+        Ok(match status {
+            RecurringStatus::Active => format!("▶️ Recurring rule #{} resumed", args.rule_id),
+            RecurringStatus::Paused => format!("⏸️ Recurring rule #{} paused", args.rule_id),
+            RecurringStatus::Cancelled => format!("⏹️ Recurring rule #{} cancelled", args.rule_id),
+        })
+    }
+
+    // The first digest covers the week or month in progress
+    async fn set_digest(
+        &self,
+        args: SetDigestArgs,
+        ctx: &SessionContext,
+    ) -> Result<String, ToolError> {
+        let next_run_date = args.frequency.next_run_after(Local::now().date_naive());
+        self.database
+            .set_digest(ctx.user_id, args.frequency, next_run_date)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        Ok(format!(
+            "📊 You'll get a {} digest {}, the first on {}",
+            args.frequency.as_str(),
+            match args.frequency {
+                DigestFrequency::Weekly => "every Monday",
+                DigestFrequency::Monthly => "on the 1st of every month",
+            },
+            next_run_date.format("%d/%m/%Y")
+        ))
+    }
+
+    async fn stop_digest(&self, ctx: &SessionContext) -> Result<String, ToolError> {
+        let deleted = self
+            .database
+            .delete_digest(ctx.user_id)
+            .await
+            .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+        Ok(if deleted {
+            "⏹️ Digest stopped".to_string()
+        } else {
+            "No digest is set up".to_string()
+        })
+    }
+
     async fn get_categories(&self, ctx: &SessionContext) -> Result<String, ToolError> {
         let categories = self
             .database
@@ -202,3 +1731,114 @@ impl ToolExecutor {
         Ok(categories.join("\n"))
     }
 }
+
+// First and last day of the month containing the date
+fn month_bounds(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let start = date.with_day(1).unwrap_or(date);
+    let next_month = start.checked_add_months(Months::new(1)).unwrap_or(start);
+    (start, next_month.pred_opt().unwrap_or(date))
+}
+
+// Keeps errors the user should see apart, such as an unknown account which is
+// answered with the user's accounts
+fn database_error(e: DatabaseError) -> ToolError {
+    match e {
+        DatabaseError::UnknownAccount(name) => ToolError::UnknownAccount(name),
+        DatabaseError::NotExpenseOwner(owner) => ToolError::NotExpenseOwner(owner),
+        DatabaseError::ExpenseNotFound(expense_id) => ToolError::ExpenseNotFound(expense_id),
+        DatabaseError::CashTransactionNotFound(transaction_id) => {
+            ToolError::CashTransactionNotFound(transaction_id)
+        }
+        DatabaseError::SettlementNotFound(settlement_id) => {
+            ToolError::SettlementNotFound(settlement_id)
+        }
+        e => ToolError::DatabaseError(e.to_string()),
+    }
+}
+
+// Balance from the user's side, positive when the person owes the user
+fn owes_line(person: &str, balance: Money) -> String {
+    if balance.is_zero() {
+        format!("You and {} are settled up", person)
+    } else if balance.is_negative() {
+        format!("You owe {} {}", person, -balance)
+    } else {
+        format!("{} owes you {}", person, balance)
+    }
+}
+
+pub fn not_owner_response(owner: &str) -> String {
+    format!("🔒 Only {} can change this expense", owner)
+}
+
+fn history_label(column: &str) -> &str {
+    match column {
+        "amount_minor" => "amount",
+        "expense_date" => "date",
+        other => other,
+    }
+}
+
+// Display form of a stored column value, amounts in rupees and dates as dd/mm/yyyy
+fn history_value(column: &str, values: &Map<String, Value>) -> Option<String> {
+    let value = values.get(column)?;
+    match column {
+        "amount_minor" => {
+            let currency = values
+                .get("currency")
+                .and_then(Value::as_str)
+                .and_then(Currency::from_code)
+                .unwrap_or_default();
+            Some(Money::from_minor(value.as_i64()?, currency).to_string())
+        }
+        "expense_date" => {
+            let date = value.as_str()?;
+            Some(
+                date.parse::<NaiveDate>()
+                    .map(|d| d.format("%d/%m/%Y").to_string())
+                    .unwrap_or_else(|_| date.to_string()),
+            )
+        }
+        _ => value.as_str().map(str::to_string),
+    }
+}
+
+// e.g. "₹15000 rent every month", "₹2000 cash every week" or "₹50000 income from Acme every month"
+fn describe_recurring(
+    kind: RecurringKind,
+    amount: Money,
+    description: Option<&str>,
+    frequency: RecurringFrequency,
+) -> String {
+    let what = match kind {
+        RecurringKind::Expense => description.map_or("expense".to_string(), description_item),
+        RecurringKind::Cash => "cash".to_string(),
+        RecurringKind::Income => format!("income from {}", description.unwrap_or("income")),
+    };
+    let every = match frequency {
+        RecurringFrequency::Daily => "every day",
+        RecurringFrequency::Weekly => "every week",
+        RecurringFrequency::Monthly => "every month",
+        RecurringFrequency::Yearly => "every year",
+    };
+    format!("{} {} {}", amount, what, every)
+}
+
+// e.g. "saved ₹30000 (60%)" or "overspent ₹2000", the rate needs some income
+fn savings_line(income: Money, expenses: Money) -> String {
+    let saved = income - expenses;
+    if saved.is_negative() {
+        return format!("overspent {}", -saved);
+    }
+    if income.is_zero() {
+        return format!("saved {}", saved);
+    }
+    format!("saved {} ({}%)", saved, percent_of(saved, income))
+}
+
+pub(super) fn percent_of(part: Money, whole: Money) -> i64 {
+    if whole.is_zero() {
+        return 0;
+    }
+    part.minor() * 100 / whole.minor()
+}
@@ -1,62 +1,139 @@
-use crate::database::{CashTransaction, Expense};
+use crate::core::Money;
+use crate::database::{CashTransaction, Expense, Income, Settlement, Transfer};
 
 #[derive(Debug, Clone)]
 pub enum ActionType {
     Expense,
     CashTransaction,
+    Transfer,
+    Income,
+    Settlement,
 }
 
 #[derive(Debug, Clone)]
-pub struct FinalizeAction {
+pub struct CreatedRecord {
     pub record_id: i64,
     pub action_type: ActionType,
 }
 
+// Records created while fulfilling a request, to be linked to the bot reply
+#[derive(Debug, Clone)]
+pub struct FinalizeAction {
+    pub records: Vec<CreatedRecord>,
+}
+
+// Category picked for a newly added expense, offered for confirmation with alternatives
+#[derive(Debug, Clone)]
+pub struct CategoryChoice {
+    pub expense_id: i64,
+    pub category: String,
+    pub alternatives: Vec<String>,
+}
+
+// Balance stated by the user that differs from the ledger, offered as an adjustment
+#[derive(Debug, Clone)]
+pub struct Reconciliation {
+    pub account_id: i64,
+    pub stated: Money,
+}
+
+// Page of a long expense listing shown in a reply, listing_id identifies the listing
+// for its next/prev buttons
+#[derive(Debug, Clone)]
+pub struct ListingPage {
+    pub listing_id: i64,
+    pub page: usize,
+    pub pages: usize,
+}
+
 pub struct FulfilmentResult {
     pub response: String,
     pub finalize: Option<FinalizeAction>,
     pub image: Option<Vec<u8>>,
+    pub category_choice: Option<CategoryChoice>,
+    pub reconciliation: Option<Reconciliation>,
+    pub listing_page: Option<ListingPage>,
+}
+
+// Preview of an uploaded import, pending is true when rows await confirmation
+pub struct ImportPreview {
+    pub response: String,
+    pub pending: bool,
+}
+
+// Ledger export ready to send as a document
+pub struct ExportFile {
+    pub file_name: String,
+    pub content: Vec<u8>,
+    pub summary: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum RecordContext {
     Expense(Expense),
     CashTransaction(CashTransaction),
+    Transfer(Transfer),
+    Income(Income),
+    Settlement(Settlement),
 }
 
 #[derive(Debug, Clone)]
 pub struct SessionContext {
+    // Owner of the ledger: the chat, which is the user in a private chat
     pub user_id: i64,
+    // Member who sent the message, None for entries the bot makes on its own
+    pub member_id: Option<i64>,
     pub user_message_id: i64,
     pub replied_record: Option<RecordContext>,
 }
 
+impl SessionContext {
+    // The member acting, the chat itself when there is no member
+    pub fn acting_member(&self) -> i64 {
+        self.member_id.unwrap_or(self.user_id)
+    }
+}
+
 pub mod args {
 
-    use serde::Deserialize;
+    use crate::core::Money;
+    use crate::database::{AccountKind, DigestFrequency, RecurringFrequency, RecurringKind};
+    use crate::request::parser::parse_date;
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer, de};
 
     // Tool argument structs
     #[derive(Debug, Deserialize, Clone)]
     pub struct AddCashArgs {
-        pub amount: f64,
-        pub date: String,
+        pub amount: Money,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub date: NaiveDate,
+        // None for the user's default account
+        #[serde(default)]
+        pub account: Option<String>,
     }
 
     #[derive(Debug, Deserialize, Clone)]
     pub struct AddExpenseArgs {
-        pub amount: f64,
+        pub amount: Money,
         pub description: String,
         pub category: String,
-        pub date: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub date: NaiveDate,
+        // None for the user's default account
+        #[serde(default)]
+        pub account: Option<String>,
     }
 
     #[derive(Debug, Deserialize, Clone)]
     pub struct ModifyExpenseArgs {
         pub expense_id: i64,
-        pub amount: Option<i64>,
+        pub amount: Option<Money>,
         pub description: Option<String>,
         pub category: Option<String>,
-        pub date: Option<String>,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub date: Option<NaiveDate>,
+        pub account: Option<String>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -64,16 +141,294 @@ pub mod args {
         pub expense_id: i64,
     }
 
+    #[derive(Debug, Deserialize)]
+    pub struct GetExpenseHistoryArgs {
+        pub expense_id: i64,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct ModifyCashArgs {
+        pub transaction_id: i64,
+        pub amount: Option<Money>,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub date: Option<NaiveDate>,
+        pub account: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteCashArgs {
+        pub transaction_id: i64,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct AddIncomeArgs {
+        pub amount: Money,
+        pub source: String,
+        pub category: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub date: NaiveDate,
+        // None for the user's default account
+        #[serde(default)]
+        pub account: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct ModifyIncomeArgs {
+        pub income_id: i64,
+        pub amount: Option<Money>,
+        pub source: Option<String>,
+        pub category: Option<String>,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub date: Option<NaiveDate>,
+        pub account: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteIncomeArgs {
+        pub income_id: i64,
+    }
+
+    // Expense paid by the user and shared with other people
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct SplitExpenseArgs {
+        pub amount: Money,
+        pub description: String,
+        pub category: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub date: NaiveDate,
+        #[serde(default)]
+        pub account: Option<String>,
+        // Everyone the expense is shared with, not including the user
+        pub people: Vec<String>,
+        // Fixed amounts for some of the people, the rest is split equally
+        #[serde(default)]
+        pub shares: Vec<PersonShareArgs>,
+        // False when the user paid only for the others
+        #[serde(default = "default_true")]
+        pub include_self: bool,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct PersonShareArgs {
+        pub person: String,
+        pub amount: Money,
+    }
+
+    // Amount None settles the whole outstanding balance
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct SettleUpArgs {
+        pub person: String,
+        pub amount: Option<Money>,
+        // Whether the user paid the person, None follows whoever owes
+        #[serde(default)]
+        pub user_paid: Option<bool>,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub date: NaiveDate,
+        #[serde(default)]
+        pub account: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct ModifySettlementArgs {
+        pub settlement_id: i64,
+        pub amount: Option<Money>,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub date: Option<NaiveDate>,
+        pub account: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteSettlementArgs {
+        pub settlement_id: i64,
+    }
+
+    // Person None summarises everyone
+    #[derive(Debug, Deserialize)]
+    pub struct GetSplitSummaryArgs {
+        #[serde(default)]
+        pub person: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct AddTransferArgs {
+        pub amount: Money,
+        pub from_account: String,
+        pub to_account: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct ModifyTransferArgs {
+        pub transfer_id: i64,
+        pub amount: Option<Money>,
+        pub from_account: Option<String>,
+        pub to_account: Option<String>,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub date: Option<NaiveDate>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteTransferArgs {
+        pub transfer_id: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct AddAccountArgs {
+        pub name: String,
+        pub kind: AccountKind,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct SetDefaultAccountArgs {
+        pub name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetBalanceArgs {
+        pub account: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ReconcileBalanceArgs {
+        // Amount the user actually has
+        pub amount: Money,
+        // None for the Cash account
+        pub account: Option<String>,
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct GetExpenseBreakdownArgs {
-        pub start_date: String,
-        pub end_date: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetMemberBreakdownArgs {
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetIncomeReportArgs {
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
     }
 
     #[derive(Debug, Deserialize)]
     pub struct GetCategoryExpensesArgs {
         pub category: String,
-        pub start_date: String,
-        pub end_date: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
+    }
+
+    // Dates are optional, without them all expenses are searched
+    #[derive(Debug, Deserialize)]
+    pub struct SearchExpensesArgs {
+        pub query: String,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub start_date: Option<NaiveDate>,
+        #[serde(default, deserialize_with = "deserialize_optional_date")]
+        pub end_date: Option<NaiveDate>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetCategoryBarChartArgs {
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum TrendGranularity {
+        Daily,
+        Weekly,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetSpendingTrendArgs {
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
+        pub granularity: Option<TrendGranularity>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetMonthlyCategoryTrendArgs {
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub end_date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct SetBudgetArgs {
+        pub category: String,
+        pub amount: Money,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteBudgetArgs {
+        pub category: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetBudgetStatusArgs {
+        pub category: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct AddRecurringArgs {
+        pub kind: RecurringKind,
+        pub amount: Money,
+        pub description: Option<String>,
+        pub category: Option<String>,
+        pub frequency: RecurringFrequency,
+        #[serde(deserialize_with = "deserialize_date")]
+        pub start_date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RecurringRuleArgs {
+        pub rule_id: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct SetDigestArgs {
+        pub frequency: DigestFrequency,
+    }
+
+    fn default_true() -> bool {
+        true
+    }
+
+    // Dates from the LLM are validated here, before anything reaches the database
+    fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        parse_date(&date).ok_or_else(|| {
+            de::Error::custom(format!("invalid date '{}', expected dd/mm/yyyy", date))
+        })
+    }
+
+    fn deserialize_optional_date<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<NaiveDate>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(date) => parse_date(&date).map(Some).ok_or_else(|| {
+                de::Error::custom(format!("invalid date '{}', expected dd/mm/yyyy", date))
+            }),
+            None => Ok(None),
+        }
     }
 }
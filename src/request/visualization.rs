@@ -1,8 +1,12 @@
-use crate::database::CategorySummary;
+use crate::core::Money;
+use crate::database::{CategorySummary, MonthlyCategorySummary};
+use chrono::NaiveDate;
 use image::ExtendedColorType;
 use image::ImageEncoder;
 use image::codecs::png::PngEncoder;
+use plotters::coord::Shift;
 use plotters::prelude::*;
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -16,6 +20,9 @@ pub enum VisualizationError {
 
 const CHART_WIDTH: u32 = 800;
 const CHART_HEIGHT: u32 = 600;
+const MAX_X_LABELS: usize = 12;
+const AMOUNT_AXIS_LABEL: &str = "Amount (Rs)";
+const LEGEND_HEADROOM_PER_ENTRY: f64 = 0.05;
 const COLORS: &[RGBColor] = &[
     RGBColor(75, 192, 192),
     RGBColor(255, 99, 132),
@@ -34,16 +41,8 @@ pub fn generate_pie_chart(data: &[CategorySummary]) -> Result<Vec<u8>, Visualiza
         return Err(VisualizationError::NoData);
     }
 
-    let mut buffer = vec![0u8; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
-    let mut png_buffer = Vec::new();
-    {
-        let root = BitMapBackend::with_buffer(&mut buffer, (CHART_WIDTH, CHART_HEIGHT))
-            .into_drawing_area();
-
-        root.fill(&WHITE)
-            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
-
-        let total: i64 = data.iter().map(|s| s.total).sum();
+    render_png(|root| {
+        let total: f64 = data.iter().map(|s| s.total.as_f64()).sum();
         let center = ((CHART_WIDTH / 2) as i32, (CHART_HEIGHT / 2) as i32);
         let radius = 180.0;
 
@@ -51,10 +50,10 @@ pub fn generate_pie_chart(data: &[CategorySummary]) -> Result<Vec<u8>, Visualiza
 
         // Draw pie slices
         for (idx, summary) in data.iter().enumerate() {
-            let angle_size = (summary.total as f64 / total as f64) * 360.0;
+            let angle_size = (summary.total.as_f64() / total) * 360.0;
             let color = COLORS[idx % COLORS.len()];
 
-            draw_pie_slice(&root, center, radius, current_angle, angle_size, &color)
+            draw_pie_slice(root, center, radius, current_angle, angle_size, &color)
                 .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
 
             current_angle += angle_size;
@@ -69,7 +68,7 @@ pub fn generate_pie_chart(data: &[CategorySummary]) -> Result<Vec<u8>, Visualiza
         for (idx, summary) in data.iter().enumerate() {
             let color = COLORS[idx % COLORS.len()];
             let y = legend_y_start + (idx as i32 * legend_spacing);
-            let percentage = (summary.total as f64 / total as f64) * 100.0;
+            let percentage = (summary.total.as_f64() / total) * 100.0;
 
             // Draw colored box
             root.draw(&Rectangle::new(
@@ -87,6 +86,245 @@ pub fn generate_pie_chart(data: &[CategorySummary]) -> Result<Vec<u8>, Visualiza
             ))
             .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
         }
+        Ok(())
+    })
+}
+
+pub fn generate_bar_chart(data: &[CategorySummary]) -> Result<Vec<u8>, VisualizationError> {
+    if data.is_empty() {
+        return Err(VisualizationError::NoData);
+    }
+
+    let max = data.iter().map(|s| s.total.as_f64()).fold(0.0, f64::max);
+    render_png(|root| {
+        let mut chart = ChartBuilder::on(root)
+            .caption("Spending by category", ("sans-serif", 24))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d((0..data.len() - 1).into_segmented(), 0.0..axis_max(max))
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(data.len())
+            .x_label_formatter(&|v| match v {
+                SegmentValue::CenterOf(idx) => data
+                    .get(*idx)
+                    .map(|s| s.category.clone())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .y_desc(AMOUNT_AXIS_LABEL)
+            .y_label_formatter(&|v| format!("{:.0}", v))
+            .label_style(("sans-serif", 14))
+            .draw()
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        chart
+            .draw_series(data.iter().enumerate().map(|(idx, summary)| {
+                let color = COLORS[idx % COLORS.len()];
+                let mut bar = Rectangle::new(
+                    [
+                        (SegmentValue::Exact(idx), 0.0),
+                        (segment_end(idx, data.len()), summary.total.as_f64()),
+                    ],
+                    color.filled(),
+                );
+                bar.set_margin(0, 0, 10, 10);
+                bar
+            }))
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+        Ok(())
+    })
+}
+
+// Line chart of spending over consecutive periods, e.g. days or weeks
+pub fn generate_line_chart(
+    caption: &str,
+    points: &[(String, Money)],
+) -> Result<Vec<u8>, VisualizationError> {
+    if points.is_empty() {
+        return Err(VisualizationError::NoData);
+    }
+
+    let max = points.iter().map(|(_, m)| m.as_f64()).fold(0.0, f64::max);
+    let last = (points.len() as i32 - 1).max(1);
+    render_png(|root| {
+        let mut chart = ChartBuilder::on(root)
+            .caption(caption, ("sans-serif", 24))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d(0..last, 0.0..axis_max(max))
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        chart
+            .configure_mesh()
+            .x_labels(points.len().min(MAX_X_LABELS))
+            .x_label_formatter(&|idx| {
+                points
+                    .get(*idx as usize)
+                    .map(|(label, _)| label.clone())
+                    .unwrap_or_default()
+            })
+            .y_desc(AMOUNT_AXIS_LABEL)
+            .y_label_formatter(&|v| format!("{:.0}", v))
+            .label_style(("sans-serif", 14))
+            .draw()
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        let color = COLORS[3];
+        let coords: Vec<(i32, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, total))| (idx as i32, total.as_f64()))
+            .collect();
+        chart
+            .draw_series(std::iter::once(PathElement::new(
+                coords.clone(),
+                color.stroke_width(3),
+            )))
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+        chart
+            .draw_series(
+                coords
+                    .into_iter()
+                    .map(|point| Circle::new(point, 4, color.filled())),
+            )
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+        Ok(())
+    })
+}
+
+// Stacked bars of category totals, one bar per month
+pub fn generate_stacked_bar_chart(
+    data: &[MonthlyCategorySummary],
+) -> Result<Vec<u8>, VisualizationError> {
+    if data.is_empty() {
+        return Err(VisualizationError::NoData);
+    }
+
+    let mut months: Vec<NaiveDate> = data.iter().map(|s| s.month).collect();
+    months.sort();
+    months.dedup();
+
+    // Largest categories first so they sit at the bottom of each bar
+    let mut category_totals: HashMap<&str, Money> = HashMap::new();
+    for summary in data {
+        *category_totals.entry(&summary.category).or_default() += summary.total;
+    }
+    let mut categories: Vec<(&str, Money)> = category_totals.into_iter().collect();
+    categories.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut month_totals = vec![0.0; months.len()];
+    for summary in data {
+        if let Ok(idx) = months.binary_search(&summary.month) {
+            month_totals[idx] += summary.total.as_f64();
+        }
+    }
+    // Extra headroom so the legend does not cover the bars
+    let max = month_totals.iter().copied().fold(0.0, f64::max)
+        * (1.0 + LEGEND_HEADROOM_PER_ENTRY * categories.len() as f64);
+
+    render_png(|root| {
+        let mut chart = ChartBuilder::on(root)
+            .caption("Monthly spending by category", ("sans-serif", 24))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d((0..months.len() - 1).into_segmented(), 0.0..axis_max(max))
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(months.len())
+            .x_label_formatter(&|v| match v {
+                SegmentValue::CenterOf(idx) => months
+                    .get(*idx)
+                    .map(|m| m.format("%b %Y").to_string())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .y_desc(AMOUNT_AXIS_LABEL)
+            .y_label_formatter(&|v| format!("{:.0}", v))
+            .label_style(("sans-serif", 14))
+            .draw()
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        let mut stack_base = vec![0.0; months.len()];
+        for (color_idx, (category, _)) in categories.iter().enumerate() {
+            let color = COLORS[color_idx % COLORS.len()];
+            let mut bars = Vec::new();
+            for summary in data.iter().filter(|s| s.category == *category) {
+                let Ok(idx) = months.binary_search(&summary.month) else {
+                    continue;
+                };
+                let bottom = stack_base[idx];
+                let top = bottom + summary.total.as_f64();
+                stack_base[idx] = top;
+                let mut bar = Rectangle::new(
+                    [
+                        (SegmentValue::Exact(idx), bottom),
+                        (segment_end(idx, months.len()), top),
+                    ],
+                    color.filled(),
+                );
+                bar.set_margin(0, 0, 15, 15);
+                bars.push(bar);
+            }
+            chart
+                .draw_series(bars)
+                .map_err(|e| VisualizationError::ChartError(e.to_string()))?
+                .label(category.to_string())
+                .legend(move |(x, y)| {
+                    Rectangle::new([(x, y - 6), (x + 12, y + 6)], color.filled())
+                });
+        }
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .label_font(("sans-serif", 14))
+            .draw()
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+        Ok(())
+    })
+}
+
+// Right edge of a bar in a segmented axis, the last segment ends at Last
+fn segment_end(idx: usize, len: usize) -> SegmentValue<usize> {
+    if idx + 1 >= len {
+        SegmentValue::Last
+    } else {
+        SegmentValue::Exact(idx + 1)
+    }
+}
+
+// Headroom above the largest value, and a non-empty range when everything is zero
+fn axis_max(max: f64) -> f64 {
+    if max > 0.0 { max * 1.1 } else { 1.0 }
+}
+
+// Draw onto a white RGB canvas and encode the result as PNG
+fn render_png<F>(draw: F) -> Result<Vec<u8>, VisualizationError>
+where
+    F: FnOnce(&DrawingArea<BitMapBackend, Shift>) -> Result<(), VisualizationError>,
+{
+    let mut buffer = vec![0u8; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+    let mut png_buffer = Vec::new();
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (CHART_WIDTH, CHART_HEIGHT))
+            .into_drawing_area();
+
+        root.fill(&WHITE)
+            .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
+
+        draw(&root)?;
 
         root.present()
             .map_err(|e| VisualizationError::ChartError(e.to_string()))?;
@@ -102,7 +340,7 @@ pub fn generate_pie_chart(data: &[CategorySummary]) -> Result<Vec<u8>, Visualiza
 }
 
 fn draw_pie_slice<DB: DrawingBackend>(
-    root: &DrawingArea<DB, plotters::coord::Shift>,
+    root: &DrawingArea<DB, Shift>,
     center: (i32, i32),
     radius: f64,
     start_angle: f64,
@@ -0,0 +1,130 @@
+use crate::configuration::Context;
+use crate::core::{Error, Service};
+use crate::database::{DatabaseError, DatabaseService, DigestSubscription};
+use crate::request::build_digest;
+use async_trait::async_trait;
+use chrono::{Local, NaiveDate};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::RequestError;
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+use tokio::sync::mpsc;
+use tokio::time;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+// Telegram limit for photo captions, longer digests are sent as a separate message
+const MAX_CAPTION_LEN: usize = 1024;
+
+// Sends weekly and monthly spending digests to users who asked for them
+pub struct DigestService {
+    bot: Bot,
+    database: Arc<DatabaseService>,
+    error_channel: mpsc::Sender<String>,
+}
+
+#[async_trait]
+impl Service for DigestService {
+    type Context = Context;
+
+    async fn new(context: Context, error_channel: mpsc::Sender<String>) -> Self {
+        let bot_token = env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN not found");
+        Self {
+            bot: Bot::new(bot_token),
+            database: context.database.clone(),
+            error_channel,
+        }
+    }
+
+    async fn run(self) -> Result<(), Error> {
+        let mut interval = time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.send_due_digests(Local::now().date_naive()).await {
+                let _ = self
+                    .error_channel
+                    .send(format!("Digest error: {}", e))
+                    .await;
+            }
+        }
+    }
+}
+
+impl DigestService {
+    async fn send_due_digests(&self, today: NaiveDate) -> Result<(), DatabaseError> {
+        for digest in self.database.get_due_digests(today).await? {
+            // One user's failure must not hold back the other subscribers
+            if let Err(e) = self.send_digest(&digest, today).await {
+                let _ = self
+                    .error_channel
+                    .send(format!("Digest error for user {}: {}", digest.user_id, e))
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_digest(
+        &self,
+        digest: &DigestSubscription,
+        today: NaiveDate,
+    ) -> Result<(), Error> {
+        // Digests missed while the bot was down are skipped, only the latest is sent
+        let next_run_date = digest.frequency.next_run_after(today);
+        // Claim the run first so a restart never sends the digest twice
+        if !self
+            .database
+            .advance_digest(digest.user_id, digest.next_run_date, next_run_date)
+            .await
+            .map_err(Error::from)?
+        {
+            return Ok(());
+        }
+        let run_date = digest.frequency.latest_run(today);
+        if let Err(e) = self.deliver(digest, run_date).await {
+            // Give the run back so the next check retries it
+            self.database
+                .advance_digest(digest.user_id, next_run_date, digest.next_run_date)
+                .await
+                .map_err(Error::from)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn deliver(&self, digest: &DigestSubscription, run_date: NaiveDate) -> Result<(), Error> {
+        let (text, chart) =
+            build_digest(&self.database, digest.user_id, digest.frequency, run_date)
+                .await
+                .map_err(Error::from)?;
+        self.send(ChatId(digest.user_id), text, chart)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn send(
+        &self,
+        chat_id: ChatId,
+        text: String,
+        chart: Option<Vec<u8>>,
+    ) -> Result<(), RequestError> {
+        match chart {
+            Some(chart) if text.chars().count() <= MAX_CAPTION_LEN => self
+                .bot
+                .send_photo(chat_id, InputFile::memory(chart))
+                .caption(text)
+                .await
+                .map(|_| ()),
+            Some(chart) => match self.bot.send_message(chat_id, text).await {
+                Ok(_) => self
+                    .bot
+                    .send_photo(chat_id, InputFile::memory(chart))
+                    .await
+                    .map(|_| ()),
+                Err(e) => Err(e),
+            },
+            None => self.bot.send_message(chat_id, text).await.map(|_| ()),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod digest;
+mod recurring;
+
+pub use digest::DigestService;
+pub use recurring::RecurringService;
@@ -0,0 +1,198 @@
+use crate::configuration::Context;
+use crate::core::{Error, Service};
+use crate::database::{DatabaseError, DatabaseService, RecurringKind, RecurringRule};
+use crate::request::types::SessionContext;
+use crate::request::types::args::{AddCashArgs, AddExpenseArgs, AddIncomeArgs};
+use async_trait::async_trait;
+use chrono::{Local, NaiveDate};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::prelude::*;
+use tokio::sync::mpsc;
+use tokio::time;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Materialises due recurring rules into expenses and cash transactions
+pub struct RecurringService {
+    bot: Bot,
+    database: Arc<DatabaseService>,
+    error_channel: mpsc::Sender<String>,
+}
+
+#[async_trait]
+impl Service for RecurringService {
+    type Context = Context;
+
+    async fn new(context: Context, error_channel: mpsc::Sender<String>) -> Self {
+        let bot_token = env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN not found");
+        Self {
+            bot: Bot::new(bot_token),
+            database: context.database.clone(),
+            error_channel,
+        }
+    }
+
+    async fn run(self) -> Result<(), Error> {
+        let mut interval = time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.run_due_rules(Local::now().date_naive()).await {
+                let _ = self
+                    .error_channel
+                    .send(format!("Recurring rules error: {}", e))
+                    .await;
+            }
+        }
+    }
+}
+
+impl RecurringService {
+    async fn run_due_rules(&self, today: NaiveDate) -> Result<(), DatabaseError> {
+        for rule in self.database.get_due_recurring_rules(today).await? {
+            // One rule's failure must not hold back the others
+            if let Err(e) = self.run_rule(&rule, today).await {
+                let _ = self
+                    .error_channel
+                    .send(format!("Recurring rule #{} error: {}", rule.id, e))
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_rule(&self, rule: &RecurringRule, today: NaiveDate) -> Result<(), DatabaseError> {
+        // Runs missed while the bot was down are skipped, only the latest is created
+        let run_date =
+            rule.frequency
+                .latest_on_or_before(rule.next_run_date, rule.anchor_day, today);
+        let next_run_date = rule.frequency.next_date(run_date, rule.anchor_day);
+        // Claim the run first so a restart never creates the entry twice
+        if !self
+            .database
+            .advance_recurring_rule(rule.id, rule.next_run_date, next_run_date)
+            .await?
+        {
+            return Ok(());
+        }
+        if let Err(e) = self.materialise(rule, run_date).await {
+            // Give the run back so the next check retries it
+            self.database
+                .advance_recurring_rule(rule.id, next_run_date, rule.next_run_date)
+                .await?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Fails only when the entry could not be created, a failed notification is reported
+    // on the error channel instead
+    async fn materialise(
+        &self,
+        rule: &RecurringRule,
+        date: NaiveDate,
+    ) -> Result<(), DatabaseError> {
+        // Not created from a message, replies to the notification link it instead
+        let session_context = SessionContext {
+            user_id: rule.user_id,
+            member_id: None,
+            user_message_id: 0,
+            replied_record: None,
+        };
+        let date_text = date.format("%d/%m/%Y");
+
+        let (record_id, text) = match rule.kind {
+            RecurringKind::Expense => {
+                let args = AddExpenseArgs {
+                    amount: rule.amount,
+                    description: rule.description.clone().unwrap_or_default(),
+                    category: rule.category.clone().unwrap_or_default(),
+                    date,
+                    account: None,
+                };
+                let expense_id = self
+                    .database
+                    .add_expense(&args, &session_context, "recurring")
+                    .await?;
+                let text = format!(
+                    "🔁 Recurring expense added: {} in {} on {}",
+                    args.description, args.category, date_text
+                );
+                (expense_id, text)
+            }
+            RecurringKind::Cash => {
+                let args = AddCashArgs {
+                    amount: rule.amount,
+                    date,
+                    account: None,
+                };
+                let cash_id = self
+                    .database
+                    .add_cash_transaction(&args, &session_context)
+                    .await?;
+                let text = format!(
+                    "🔁 Recurring cash entry: added {} to cash balance on {}",
+                    args.amount, date_text
+                );
+                (cash_id, text)
+            }
+            RecurringKind::Income => {
+                let args = AddIncomeArgs {
+                    amount: rule.amount,
+                    source: rule.description.clone().unwrap_or_default(),
+                    category: rule.category.clone().unwrap_or_default(),
+                    date,
+                    account: None,
+                };
+                let income_id = self.database.add_income(&args, &session_context).await?;
+                let text = format!(
+                    "🔁 Recurring income added: {} from {} on {}",
+                    args.amount, args.source, date_text
+                );
+                (income_id, text)
+            }
+        };
+
+        let Some(bot_message_id) = self.notify(ChatId(rule.user_id), text).await else {
+            return Ok(());
+        };
+        let linked = match rule.kind {
+            RecurringKind::Expense => {
+                self.database
+                    .update_expense_bot_message(record_id, bot_message_id)
+                    .await
+            }
+            RecurringKind::Cash => {
+                self.database
+                    .update_cash_bot_message(record_id, bot_message_id)
+                    .await
+            }
+            RecurringKind::Income => {
+                self.database
+                    .update_income_bot_message(record_id, bot_message_id)
+                    .await
+            }
+        };
+        if let Err(e) = linked {
+            let _ = self
+                .error_channel
+                .send(format!("Failed to link recurring notification: {}", e))
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn notify(&self, chat_id: ChatId, text: String) -> Option<i64> {
+        match self.bot.send_message(chat_id, text).await {
+            Ok(msg) => Some(msg.id.0 as i64),
+            Err(e) => {
+                let _ = self
+                    .error_channel
+                    .send(format!("Failed to send recurring notification: {}", e))
+                    .await;
+                None
+            }
+        }
+    }
+}